@@ -1,7 +1,11 @@
-show me what you got! "Please input your brainfuck program: "
-portal gun input
+if (argv squanch more 0) :<
+	input squanch readFile(argv[0])
+>: else :<
+	show me what you got! "Please input your brainfuck program: "
+	portal gun input
 
-show me what you got ""
+	show me what you got ""
+>:
 
 size squanch (input squanch)
 count squanch 0
@@ -0,0 +1,323 @@
+use crate::{
+    error::{ErrorKind, SwResult},
+    grammar, json,
+    state::State,
+    value::{Func, Type, Value},
+    BUILTINS, BUILTINS_FILE,
+};
+use std::{
+    convert::TryFrom,
+    env,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::Path,
+    process::{Command, Stdio},
+    thread,
+};
+
+/// Things that a program can be stopped from doing, for when it can't be trusted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Capability {
+    /// Running other programs
+    Subprocess,
+    /// Loading microverses, which run native code that can do anything at all
+    NativeCode,
+    /// Writing to files
+    WriteFiles,
+}
+
+/// A builtin function that is written in Rust instead of in `builtins.y`.
+pub struct Builtin {
+    pub name: &'static str,
+    pub params: &'static [&'static str],
+    function: fn(&[Value]) -> SwResult<Value>,
+    /// What the program has to be allowed to do to call this
+    pub capability: Option<Capability>,
+}
+
+impl Builtin {
+    pub fn call(&self, args: &[Value]) -> SwResult<Value> {
+        if args.len() != self.params.len() {
+            return Err(ErrorKind::InvalidArguments(
+                self.name.into(),
+                self.params.len(),
+                args.len(),
+            )
+            .into());
+        }
+
+        (self.function)(args)
+    }
+
+    pub fn signature(&self) -> String {
+        format!("{}({})", self.name, self.params.join(", "))
+    }
+}
+
+pub static NATIVE: [Builtin; 12] = [
+    Builtin {
+        name: "readFile",
+        params: &["path"],
+        function: read_file,
+        capability: None,
+    },
+    Builtin {
+        name: "readLines",
+        params: &["path"],
+        function: read_lines,
+        capability: None,
+    },
+    Builtin {
+        name: "writeFile",
+        params: &["path", "contents"],
+        function: write_file,
+        capability: Some(Capability::WriteFiles),
+    },
+    Builtin {
+        name: "appendFile",
+        params: &["path", "contents"],
+        function: append_file,
+        capability: Some(Capability::WriteFiles),
+    },
+    Builtin {
+        name: "fileExists",
+        params: &["path"],
+        function: file_exists,
+        capability: None,
+    },
+    Builtin {
+        name: "listDir",
+        params: &["path"],
+        function: list_dir,
+        capability: None,
+    },
+    Builtin {
+        name: "exit",
+        params: &["code"],
+        function: exit,
+        capability: None,
+    },
+    Builtin {
+        name: "env",
+        params: &["name"],
+        function: env,
+        capability: None,
+    },
+    Builtin {
+        name: "runProcess",
+        params: &["command", "args"],
+        function: run_process,
+        capability: Some(Capability::Subprocess),
+    },
+    Builtin {
+        name: "runProcessWith",
+        params: &["command", "args", "input", "dir"],
+        function: run_process_with,
+        capability: Some(Capability::Subprocess),
+    },
+    Builtin {
+        name: "parseJson",
+        params: &["text"],
+        function: parse_json,
+        capability: None,
+    },
+    Builtin {
+        name: "toJson",
+        params: &["value"],
+        function: to_json,
+        capability: None,
+    },
+];
+
+/// Define every builtin in `state`, both the native ones and the ones from `builtins.y`.
+pub fn load(state: &mut State) {
+    for builtin in &NATIVE {
+        state.insert(builtin.name, Func::from(builtin));
+    }
+
+    let statements = grammar::file(BUILTINS).expect("builtins should always parse");
+    if let Err(e) = state.run(&statements) {
+        e.panic(BUILTINS_FILE);
+    }
+}
+
+/// The native builtin called `name`, which can be called from anywhere, even inside functions.
+pub(crate) fn native(name: &str) -> Option<&'static Builtin> {
+    NATIVE.iter().find(|builtin| builtin.name == name)
+}
+
+fn string(value: &Value) -> SwResult<&str> {
+    match *value {
+        Value::Str(ref s) => Ok(s.as_str()),
+        _ => Err(ErrorKind::UnexpectedType {
+            expected: Type::Str,
+            actual: value.get_type(),
+        }
+        .into()),
+    }
+}
+
+/// A string that can be left out by passing `morty` instead.
+fn optional_string(value: &Value) -> SwResult<Option<&str>> {
+    match *value {
+        Value::Bool(false) => Ok(None),
+        _ => string(value).map(Some),
+    }
+}
+
+fn read_file(args: &[Value]) -> SwResult<Value> {
+    Ok(Value::Str(fs::read_to_string(string(&args[0])?)?.into()))
+}
+
+fn read_lines(args: &[Value]) -> SwResult<Value> {
+    let contents = fs::read_to_string(string(&args[0])?)?;
+
+    Ok(contents
+        .lines()
+        .map(String::from)
+        .collect::<Vec<_>>()
+        .into())
+}
+
+fn write_file(args: &[Value]) -> SwResult<Value> {
+    let mut file = fs::File::create(string(&args[0])?)?;
+    args[1].write(&mut file)?;
+
+    Ok(Value::Bool(true))
+}
+
+fn append_file(args: &[Value]) -> SwResult<Value> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(string(&args[0])?)?;
+    args[1].write(&mut file)?;
+
+    Ok(Value::Bool(true))
+}
+
+fn file_exists(args: &[Value]) -> SwResult<Value> {
+    Ok(Value::Bool(Path::new(string(&args[0])?).exists()))
+}
+
+fn list_dir(args: &[Value]) -> SwResult<Value> {
+    let mut names = Vec::new();
+
+    for entry in fs::read_dir(string(&args[0])?)? {
+        names.push(entry?.file_name().to_string_lossy().into_owned());
+    }
+    names.sort();
+
+    Ok(names.into())
+}
+
+/// Stop the program with an exit status. This is an error as far as the interpreter is concerned
+/// so that it unwinds everything that is running, but `normal plan` won't catch it. A status that
+/// doesn't fit in an `i32` is a regular error instead, rather than being cut down to something else.
+fn exit(args: &[Value]) -> SwResult<Value> {
+    match args[0] {
+        Value::Int(code) => match i32::try_from(code) {
+            Ok(code) => Err(ErrorKind::Exit(code).into()),
+            Err(_) => Err(ErrorKind::InvalidExitStatus(code).into()),
+        },
+        ref code => Err(ErrorKind::UnexpectedType {
+            expected: Type::Int,
+            actual: code.get_type(),
+        }
+        .into()),
+    }
+}
+
+/// The value of an environment variable, or `morty` if it isn't set.
+fn env(args: &[Value]) -> SwResult<Value> {
+    match env::var_os(string(&args[0])?) {
+        Some(value) => Ok(Value::Str(value.to_string_lossy().into_owned().into())),
+        None => Ok(Value::Bool(false)),
+    }
+}
+
+fn parse_json(args: &[Value]) -> SwResult<Value> {
+    Ok(json::parse(string(&args[0])?)?)
+}
+
+fn to_json(args: &[Value]) -> SwResult<Value> {
+    Ok(Value::Str(json::serialize(&args[0])?.into()))
+}
+
+fn run_process(args: &[Value]) -> SwResult<Value> {
+    spawn(&args[0], &args[1], None, None)
+}
+
+fn run_process_with(args: &[Value]) -> SwResult<Value> {
+    spawn(
+        &args[0],
+        &args[1],
+        optional_string(&args[2])?,
+        optional_string(&args[3])?,
+    )
+}
+
+/// Run a program to completion, giving back a list of its stdout, its stderr and its exit status,
+/// which is `morty` if it was killed by a signal.
+fn spawn(command: &Value, args: &Value, input: Option<&str>, dir: Option<&str>) -> SwResult<Value> {
+    let args = match *args {
+        Value::List(ref args) => args.iter().map(Value::to_string),
+        _ => {
+            return Err(ErrorKind::UnexpectedType {
+                expected: Type::List,
+                actual: args.get_type(),
+            }
+            .into())
+        }
+    };
+
+    let mut command = Command::new(string(command)?);
+    command
+        .args(args)
+        .stdin(if input.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    if let Some(dir) = dir {
+        command.current_dir(dir);
+    }
+
+    let mut child = command.spawn()?;
+
+    // Writing on another thread means a program that fills up its stdout before reading all of
+    // its input can't deadlock us
+    let writer = match (child.stdin.take(), input) {
+        (Some(mut stdin), Some(input)) => {
+            let input = input.to_string();
+            Some(thread::spawn(move || stdin.write_all(input.as_bytes())))
+        }
+        _ => None,
+    };
+
+    let output = child.wait_with_output()?;
+
+    if let Some(writer) = writer {
+        // A program is allowed to exit without reading everything it was given
+        match writer.join() {
+            Ok(Err(ref e)) if e.kind() != io::ErrorKind::BrokenPipe => {
+                return Err(ErrorKind::IOError(io::Error::new(e.kind(), e.to_string())).into())
+            }
+            _ => {}
+        }
+    }
+
+    let status = match output.status.code() {
+        Some(code) => Value::Int(code.into()),
+        None => Value::Bool(false),
+    };
+
+    Ok(Value::List(vec![
+        Value::Str(String::from_utf8_lossy(&output.stdout).into_owned().into()),
+        Value::Str(String::from_utf8_lossy(&output.stderr).into_owned().into()),
+        status,
+    ]))
+}
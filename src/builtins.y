@@ -10,7 +10,7 @@ ascii (int) :<
 		return "!"
 	>:
 	if (int == 34) :<
-		return "'"
+		return "\""
 	>:
 	if (int == 35) :<
 		return "#"
@@ -184,7 +184,7 @@ ascii (int) :<
 		return "["
 	>:
 	if (int == 92) :<
-		return "\"
+		return "\\"
 	>:
 	if (int == 93) :<
 		return "]"
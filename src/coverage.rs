@@ -0,0 +1,140 @@
+use crate::{
+    grammar,
+    hook::Hook,
+    state::State,
+    statement::{walk, Statement, StatementKind},
+    util::LineIndex,
+};
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::{self, Write},
+};
+
+/// Records which statements and functions ran, so that it can be reported in lcov format.
+pub struct Coverage {
+    filename: String,
+    lines: LineIndex,
+    statements: Vec<Statement>,
+    /// How many times the statement starting at each byte offset ran
+    counts: HashMap<usize, u64>,
+    calls: HashMap<String, u64>,
+}
+
+impl Coverage {
+    pub fn new(filename: &str, source: &str) -> Self {
+        Self {
+            filename: filename.into(),
+            lines: LineIndex::new(source),
+            statements: grammar::file(source).unwrap_or_default(),
+            counts: HashMap::new(),
+            calls: HashMap::new(),
+        }
+    }
+
+    fn count(&self, statement: &Statement) -> u64 {
+        self.counts
+            .get(&statement.start())
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// How many times the first statement of a block ran, which is how many times the block was
+    /// entered. Empty blocks don't have a first statement, so they give `None`.
+    fn entered(&self, block: &[Statement]) -> Option<u64> {
+        block.first().map(|statement| self.count(statement))
+    }
+
+    pub fn write_lcov<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let mut line_counts = BTreeMap::new();
+        let mut functions = Vec::new();
+        let mut branches = Vec::new();
+
+        walk(&self.statements, &mut |statement| {
+            let line = self.lines.line(statement.start());
+            let count = self.count(statement);
+
+            let line_count = line_counts.entry(line).or_insert(0);
+            *line_count = count.max(*line_count);
+
+            match statement.kind {
+                StatementKind::Function(ref name, _, _) => {
+                    let calls = self.calls.get(name).cloned().unwrap_or_default();
+                    functions.push((line, name.clone(), calls));
+                }
+                StatementKind::If(_, ref if_body, ref else_body) => {
+                    let else_body = else_body.as_ref().map(Vec::as_slice).unwrap_or_default();
+
+                    let taken = match (self.entered(if_body), self.entered(else_body)) {
+                        (Some(if_taken), Some(else_taken)) => Some((if_taken, else_taken)),
+                        (Some(if_taken), None) => Some((if_taken, count.saturating_sub(if_taken))),
+                        (None, Some(else_taken)) => {
+                            Some((count.saturating_sub(else_taken), else_taken))
+                        }
+                        (None, None) => None,
+                    };
+
+                    if let Some((if_taken, else_taken)) = taken {
+                        branches.push((line, count, if_taken, else_taken));
+                    }
+                }
+                _ => {}
+            }
+        });
+
+        writeln!(out, "TN:")?;
+        writeln!(out, "SF:{}", self.filename)?;
+
+        for (line, name, _) in &functions {
+            writeln!(out, "FN:{},{}", line, name)?;
+        }
+        for (_, name, calls) in &functions {
+            writeln!(out, "FNDA:{},{}", calls, name)?;
+        }
+        writeln!(out, "FNF:{}", functions.len())?;
+        writeln!(
+            out,
+            "FNH:{}",
+            functions.iter().filter(|(_, _, calls)| *calls > 0).count()
+        )?;
+
+        let mut branches_hit = 0;
+        for (block, (line, count, if_taken, else_taken)) in branches.iter().enumerate() {
+            for (branch, taken) in [if_taken, else_taken].iter().enumerate() {
+                // lcov wants a dash for branches whose condition never even ran
+                if *count == 0 {
+                    writeln!(out, "BRDA:{},{},{},-", line, block, branch)?;
+                } else {
+                    writeln!(out, "BRDA:{},{},{},{}", line, block, branch, taken)?;
+                }
+
+                if **taken > 0 {
+                    branches_hit += 1;
+                }
+            }
+        }
+        writeln!(out, "BRF:{}", branches.len() * 2)?;
+        writeln!(out, "BRH:{}", branches_hit)?;
+
+        for (line, count) in &line_counts {
+            writeln!(out, "DA:{},{}", line, count)?;
+        }
+        writeln!(out, "LF:{}", line_counts.len())?;
+        writeln!(
+            out,
+            "LH:{}",
+            line_counts.values().filter(|count| **count > 0).count()
+        )?;
+
+        writeln!(out, "end_of_record")
+    }
+}
+
+impl Hook for Coverage {
+    fn statement(&mut self, _state: &mut State, statement: &Statement) {
+        *self.counts.entry(statement.start()).or_default() += 1;
+    }
+
+    fn enter_function(&mut self, name: &str) {
+        *self.calls.entry(name.into()).or_default() += 1;
+    }
+}
@@ -0,0 +1,662 @@
+use crate::{
+    builtins,
+    debugger::Mode,
+    error::ErrorKind,
+    grammar,
+    hook::Hook,
+    rpc,
+    state::State,
+    statement::{walk, Statement},
+    util::LineIndex,
+    value::Value,
+};
+use serde_json::{json, Value as Json};
+use std::{
+    cell::RefCell,
+    collections::BTreeSet,
+    fs,
+    io::{self, BufReader, Read, Write},
+    process,
+    rc::Rc,
+    sync::mpsc,
+    thread,
+};
+
+const THREAD_ID: u64 = 1;
+const LOCALS_REFERENCE: usize = 1;
+
+/// Sends messages to the client, keeping track of the sequence numbers that the protocol needs.
+struct Protocol<W> {
+    output: W,
+    seq: u64,
+}
+
+impl<W> Protocol<W>
+where
+    W: Write,
+{
+    fn send(&mut self, mut message: Json) {
+        self.seq += 1;
+        message["seq"] = json!(self.seq);
+
+        // If we can't talk to the client anymore there's nobody left to debug for
+        if rpc::write_message(&mut self.output, &message).is_err() {
+            process::exit(1);
+        }
+    }
+
+    fn event(&mut self, event: &str, body: Json) {
+        self.send(json!({
+            "type": "event",
+            "event": event,
+            "body": body,
+        }));
+    }
+
+    fn respond(&mut self, request: &Json, body: Json) {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": request["command"],
+            "success": true,
+            "body": body,
+        }));
+    }
+
+    fn fail(&mut self, request: &Json, message: String) {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": request["command"],
+            "success": false,
+            "message": message,
+        }));
+    }
+}
+
+/// Turns everything the program prints into `output` events.
+struct ProgramOutput<W> {
+    protocol: Rc<RefCell<Protocol<W>>>,
+    category: &'static str,
+}
+
+impl<W> Write for ProgramOutput<W>
+where
+    W: Write,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.protocol.borrow_mut().event(
+            "output",
+            json!({
+                "category": self.category,
+                "output": String::from_utf8_lossy(buf),
+            }),
+        );
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+enum Action {
+    Nothing,
+    Resume,
+    Disconnect,
+}
+
+struct Frame {
+    name: String,
+    line: usize,
+}
+
+#[derive(Default)]
+struct Launch {
+    args: Vec<String>,
+    stop_on_entry: bool,
+}
+
+struct Session<W> {
+    protocol: Rc<RefCell<Protocol<W>>>,
+    messages: mpsc::Receiver<Json>,
+    launch: Option<Launch>,
+    /// The path of the program being debugged, as the client gave it.
+    program: String,
+    configured: bool,
+    lines: LineIndex,
+    breakpoints: BTreeSet<usize>,
+    mode: Mode,
+    entry: bool,
+    pause_requested: bool,
+    stack: Vec<Frame>,
+    /// Lists that the client has been given a reference to, as a variable name and the indices
+    /// to get from it to the list.
+    references: Vec<(String, Vec<usize>)>,
+}
+
+/// Run a debug adapter, speaking the Debug Adapter Protocol over `input` and `output`.
+///
+/// Only `launch` is supported, and the program's own output is sent to the client as `output`
+/// events since it can't share the protocol's stream.
+pub fn serve<R, W>(input: R, output: W) -> io::Result<()>
+where
+    R: Read + Send + 'static,
+    W: Write + 'static,
+{
+    let (sender, messages) = mpsc::channel();
+
+    thread::spawn(move || {
+        let mut input = BufReader::new(input);
+        while let Ok(Some(message)) = rpc::read_message(&mut input) {
+            if sender.send(message).is_err() {
+                break;
+            }
+        }
+    });
+
+    let protocol = Rc::new(RefCell::new(Protocol { output, seq: 0 }));
+
+    let session = Rc::new(RefCell::new(Session {
+        protocol: protocol.clone(),
+        messages,
+        launch: None,
+        program: String::new(),
+        configured: false,
+        lines: LineIndex::new(""),
+        breakpoints: BTreeSet::new(),
+        mode: Mode::Continue,
+        entry: false,
+        pause_requested: false,
+        stack: Vec::new(),
+        references: Vec::new(),
+    }));
+
+    loop {
+        let mut session = session.borrow_mut();
+        if session.launch.is_some() && session.configured {
+            break;
+        }
+
+        let message = match session.messages.recv() {
+            Ok(message) => message,
+            Err(_) => return Ok(()),
+        };
+
+        if let Action::Disconnect = session.handle(&message, None) {
+            return Ok(());
+        }
+    }
+
+    let exit_code = launch(&session, &protocol);
+
+    {
+        let mut protocol = protocol.borrow_mut();
+        protocol.event("exited", json!({ "exitCode": exit_code }));
+        protocol.event("terminated", json!({}));
+    }
+
+    let mut session = session.borrow_mut();
+    while let Ok(message) = session.messages.recv() {
+        if let Action::Disconnect = session.handle(&message, None) {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+/// Run the program that the client asked for, returning its exit code.
+fn launch<W>(session: &Rc<RefCell<Session<W>>>, protocol: &Rc<RefCell<Protocol<W>>>) -> i32
+where
+    W: Write + 'static,
+{
+    let report = |message: String| {
+        protocol.borrow_mut().event(
+            "output",
+            json!({
+                "category": "stderr",
+                "output": message,
+            }),
+        );
+    };
+
+    let (program, args) = {
+        let mut session = session.borrow_mut();
+        let launch = session.launch.take().unwrap_or_default();
+
+        session.mode = if launch.stop_on_entry {
+            Mode::StepInto
+        } else {
+            Mode::Continue
+        };
+        session.entry = launch.stop_on_entry;
+
+        (session.program.clone(), launch.args)
+    };
+
+    let source = match fs::read_to_string(&program) {
+        Ok(source) => source,
+        Err(e) => {
+            report(format!("Failed to read {}: {}\n", program, e));
+            return 1;
+        }
+    };
+
+    let statements = match grammar::file(&source) {
+        Ok(statements) => statements,
+        Err(e) => {
+            report(format!(
+                "SYNTAX ERROR: {}:{}: expected {}\n",
+                program, e.location.line, e.expected
+            ));
+            return 1;
+        }
+    };
+
+    {
+        let mut session = session.borrow_mut();
+        session.lines = LineIndex::new(&source);
+        session.stack.push(Frame {
+            name: program.clone(),
+            line: 0,
+        });
+    }
+
+    let mut state = State::with_io(
+        Rc::new(RefCell::new(io::empty())),
+        Rc::new(RefCell::new(ProgramOutput {
+            protocol: protocol.clone(),
+            category: "stdout",
+        })),
+    );
+    state.set_error_output(Rc::new(RefCell::new(ProgramOutput {
+        protocol: protocol.clone(),
+        category: "stderr",
+    })));
+
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    state.parse_args(&args);
+    state.set_program(&program);
+
+    builtins::load(&mut state);
+
+    state.set_hook(session.clone());
+
+    let result = state.run(&statements);
+
+    // Native functions from microverses can't outlive their libraries, just like in run_program
+    std::mem::forget(state);
+
+    match result {
+        Ok(()) => 0,
+        Err(e) => match *e.kind() {
+            ErrorKind::Exit(code) => code,
+            _ => {
+                report(e.full_panic_message(&program));
+                1
+            }
+        },
+    }
+}
+
+impl<W> Session<W>
+where
+    W: Write,
+{
+    /// Handle a single request. `state` is only available while the program is running.
+    fn handle(&mut self, request: &Json, state: Option<&mut State>) -> Action {
+        let args = &request["arguments"];
+        let command = request["command"].as_str().unwrap_or_default();
+
+        match command {
+            "initialize" => {
+                let mut protocol = self.protocol.borrow_mut();
+                protocol.respond(
+                    request,
+                    json!({
+                        "supportsConfigurationDoneRequest": true,
+                        "supportsEvaluateForHovers": true,
+                    }),
+                );
+            }
+            "launch" => {
+                let program = args["program"].as_str().unwrap_or_default();
+                let launch_args = args["args"]
+                    .as_array()
+                    .map(|a| a.iter().filter_map(Json::as_str).map(String::from))
+                    .into_iter()
+                    .flatten()
+                    .collect();
+
+                self.program = program.into();
+                self.launch = Some(Launch {
+                    args: launch_args,
+                    stop_on_entry: args["stopOnEntry"].as_bool().unwrap_or(false),
+                });
+                self.respond(request, json!({}));
+
+                // Breakpoints can only be checked against the program once we know what it is
+                self.protocol.borrow_mut().event("initialized", json!({}));
+            }
+            "configurationDone" => {
+                self.configured = true;
+                self.respond(request, json!({}));
+            }
+            "setBreakpoints" => {
+                let path = args["source"]["path"].as_str().unwrap_or_default();
+                let lines: Vec<usize> = args["breakpoints"]
+                    .as_array()
+                    .map(|b| b.iter().filter_map(|b| b["line"].as_u64()))
+                    .into_iter()
+                    .flatten()
+                    .map(|line| line as usize)
+                    .collect();
+
+                let breakpoints: Vec<Json> = match self.statement_lines(path) {
+                    Ok(statement_lines) => {
+                        self.breakpoints.clear();
+
+                        // A line without a statement on it would never be stopped at, so the
+                        // breakpoint moves to the next one that has
+                        lines
+                            .iter()
+                            .map(|&line| match statement_lines.range(line..).next() {
+                                Some(&actual) => {
+                                    self.breakpoints.insert(actual);
+                                    json!({ "verified": true, "line": actual })
+                                }
+                                None => json!({
+                                    "verified": false,
+                                    "line": line,
+                                    "message": "There's nothing after this line to stop at, Morty",
+                                }),
+                            })
+                            .collect()
+                    }
+                    Err(message) => lines
+                        .iter()
+                        .map(|line| json!({ "verified": false, "line": line, "message": message }))
+                        .collect(),
+                };
+                self.respond(request, json!({ "breakpoints": breakpoints }));
+            }
+            "setExceptionBreakpoints" => self.respond(request, json!({})),
+            "threads" => self.respond(
+                request,
+                json!({ "threads": [{ "id": THREAD_ID, "name": "main" }] }),
+            ),
+            "stackTrace" => {
+                let frames: Vec<Json> = self
+                    .stack
+                    .iter()
+                    .enumerate()
+                    .rev()
+                    .map(|(id, frame)| {
+                        json!({
+                            "id": id,
+                            "name": frame.name,
+                            "line": frame.line,
+                            "column": 1,
+                            "source": { "path": self.program },
+                        })
+                    })
+                    .collect();
+
+                self.respond(
+                    request,
+                    json!({ "stackFrames": frames, "totalFrames": frames.len() }),
+                );
+            }
+            "scopes" => {
+                // Only the innermost function's variables are still around to look at
+                let top = self.stack.len().saturating_sub(1) as u64;
+                let scopes = if args["frameId"].as_u64() == Some(top) {
+                    json!([{
+                        "name": "Locals",
+                        "variablesReference": LOCALS_REFERENCE,
+                        "expensive": false,
+                    }])
+                } else {
+                    json!([])
+                };
+
+                self.respond(request, json!({ "scopes": scopes }));
+            }
+            "variables" => match state {
+                Some(state) => {
+                    let reference = args["variablesReference"].as_u64().unwrap_or(0) as usize;
+                    let variables = self.variables(state, reference);
+                    self.respond(request, json!({ "variables": variables }));
+                }
+                None => self.fail(request, "The program isn't running, Morty".into()),
+            },
+            "evaluate" => {
+                let expression = args["expression"].as_str().unwrap_or_default();
+
+                let result = match state {
+                    Some(state) => match grammar::expression(expression) {
+                        Ok(exp) => exp
+                            .evaluate(state)
+                            .map(|value| value.to_string())
+                            .map_err(|e| e.kind().to_string()),
+                        Err(e) => Err(format!("SYNTAX ERROR: expected {}", e.expected)),
+                    },
+                    None => Err("The program isn't running, Morty".into()),
+                };
+
+                match result {
+                    Ok(result) => self.respond(
+                        request,
+                        json!({ "result": result, "variablesReference": 0 }),
+                    ),
+                    Err(message) => self.fail(request, message),
+                }
+            }
+            "continue" | "next" | "stepIn" | "stepOut" => {
+                let depth = self.stack.len().saturating_sub(1);
+                self.mode = match command {
+                    "continue" => Mode::Continue,
+                    "next" => Mode::StepOver(depth),
+                    "stepIn" => Mode::StepInto,
+                    _ => Mode::StepOut(depth),
+                };
+
+                self.respond(request, json!({ "allThreadsContinued": true }));
+                return Action::Resume;
+            }
+            "pause" => {
+                self.pause_requested = true;
+                self.respond(request, json!({}));
+            }
+            "disconnect" | "terminate" => {
+                self.respond(request, json!({}));
+                return Action::Disconnect;
+            }
+            _ => self.fail(request, format!("I don't know how to {}, Morty", command)),
+        }
+
+        Action::Nothing
+    }
+
+    fn respond(&mut self, request: &Json, body: Json) {
+        self.protocol.borrow_mut().respond(request, body);
+    }
+
+    fn fail(&mut self, request: &Json, message: String) {
+        self.protocol.borrow_mut().fail(request, message);
+    }
+
+    /// The lines in the file at `path` that have a statement on them, if it's the program being
+    /// debugged.
+    fn statement_lines(&self, path: &str) -> Result<BTreeSet<usize>, String> {
+        if !same_file(path, &self.program) {
+            return Err(format!("Only {} is being debugged, Morty", self.program));
+        }
+
+        let source =
+            fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        let statements = grammar::file(&source).map_err(|e| {
+            format!(
+                "SYNTAX ERROR: {}:{}: expected {}",
+                path, e.location.line, e.expected
+            )
+        })?;
+
+        let index = LineIndex::new(&source);
+        let mut lines = BTreeSet::new();
+        walk(&statements, &mut |statement| {
+            lines.insert(index.line(statement.start()));
+        });
+
+        Ok(lines)
+    }
+
+    fn variables(&mut self, state: &State, reference: usize) -> Vec<Json> {
+        if reference == LOCALS_REFERENCE {
+            let mut variables = Vec::new();
+            for (name, value) in state.variables() {
+                let variable = self.variable(name, value, name.to_string(), Vec::new());
+                variables.push(variable);
+            }
+
+            return variables;
+        }
+
+        // 0 means the variable has no children, and a client could send anything else too
+        let (name, path) = match reference
+            .checked_sub(LOCALS_REFERENCE + 1)
+            .and_then(|idx| self.references.get(idx))
+        {
+            Some(x) => x.clone(),
+            None => return Vec::new(),
+        };
+
+        let mut value = match state.get(&name) {
+            Ok(value) => value,
+            Err(_) => return Vec::new(),
+        };
+
+        for &idx in &path {
+            value = match *value {
+                Value::List(ref list) if idx < list.len() => &list[idx],
+                _ => return Vec::new(),
+            };
+        }
+
+        match *value {
+            Value::List(ref list) => list
+                .iter()
+                .enumerate()
+                .map(|(idx, item)| {
+                    let mut item_path = path.clone();
+                    item_path.push(idx);
+                    self.variable(&idx.to_string(), item, name.clone(), item_path)
+                })
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    fn variable(&mut self, display: &str, value: &Value, name: String, path: Vec<usize>) -> Json {
+        let reference = match *value {
+            Value::List(_) => {
+                self.references.push((name, path));
+                self.references.len() + LOCALS_REFERENCE
+            }
+            _ => 0,
+        };
+
+        json!({
+            "name": display,
+            "value": value.to_string(),
+            "type": value.get_type().to_string(),
+            "variablesReference": reference,
+        })
+    }
+
+    fn stop(&mut self, state: &mut State, reason: &str) {
+        self.protocol.borrow_mut().event(
+            "stopped",
+            json!({
+                "reason": reason,
+                "threadId": THREAD_ID,
+                "allThreadsStopped": true,
+            }),
+        );
+
+        loop {
+            let message = match self.messages.recv() {
+                Ok(message) => message,
+                Err(_) => process::exit(0),
+            };
+
+            match self.handle(&message, Some(state)) {
+                Action::Nothing => {}
+                Action::Resume => break,
+                Action::Disconnect => process::exit(0),
+            }
+        }
+
+        self.references.clear();
+    }
+}
+
+impl<W> Hook for Session<W>
+where
+    W: Write,
+{
+    fn statement(&mut self, state: &mut State, statement: &Statement) {
+        // Requests like pause and setBreakpoints can show up while the program is running
+        while let Ok(message) = self.messages.try_recv() {
+            if let Action::Disconnect = self.handle(&message, Some(state)) {
+                process::exit(0);
+            }
+        }
+
+        let line = self.lines.line(statement.start());
+        let depth = self.stack.len().saturating_sub(1);
+
+        if let Some(frame) = self.stack.last_mut() {
+            frame.line = line;
+        }
+
+        let reason = if self.pause_requested {
+            "pause"
+        } else if self.entry {
+            "entry"
+        } else if self.breakpoints.contains(&line) {
+            "breakpoint"
+        } else if self.mode.stops_at(depth) {
+            "step"
+        } else {
+            return;
+        };
+
+        self.pause_requested = false;
+        self.entry = false;
+
+        self.stop(state, reason);
+    }
+
+    fn enter_function(&mut self, name: &str) {
+        self.stack.push(Frame {
+            name: name.into(),
+            line: 0,
+        });
+    }
+
+    fn exit_function(&mut self, _name: &str) {
+        self.stack.pop();
+    }
+}
+
+/// Whether two paths are the same file, even if one of them is relative and the other isn't.
+fn same_file(a: &str, b: &str) -> bool {
+    a == b
+        || match (fs::canonicalize(a), fs::canonicalize(b)) {
+            (Ok(a), Ok(b)) => a == b,
+            _ => false,
+        }
+}
@@ -0,0 +1,194 @@
+use crate::{grammar, hook::Hook, state::State, statement::Statement, util::LineIndex};
+use std::{
+    collections::BTreeSet,
+    io::{self, Write},
+    process,
+};
+
+const HELP: &str = "\
+Commands:
+  c, continue        run until the next breakpoint
+  s, step            run the next statement, stepping into function calls
+  n, next            run the next statement, stepping over function calls
+  o, out             run until the current function returns
+  b, break LINE      set a breakpoint on LINE
+  d, delete LINE     remove the breakpoint on LINE
+  p, print EXPR      evaluate EXPR and show the result
+  v, vars            show every variable in the current function
+  w, where           show the function calls that led here
+  l, list            show the source around the current line
+  q, quit            stop the program
+Anything else is run as a schwift statement, so `x squanch 10` changes x.";
+
+pub(crate) enum Mode {
+    Continue,
+    StepInto,
+    StepOver(usize),
+    StepOut(usize),
+}
+
+impl Mode {
+    /// Whether to stop at a statement that is `depth` function calls deep.
+    pub(crate) fn stops_at(&self, depth: usize) -> bool {
+        match *self {
+            Mode::Continue => false,
+            Mode::StepInto => true,
+            Mode::StepOver(d) => depth <= d,
+            Mode::StepOut(d) => depth < d,
+        }
+    }
+}
+
+/// An interactive debugger that pauses a program at breakpoints and lets you poke at it.
+///
+/// Commands are read from the program's input, and everything the debugger has to say goes to
+/// stderr so that it doesn't get mixed up with the program's own output.
+pub struct Debugger {
+    filename: String,
+    source: String,
+    lines: LineIndex,
+    breakpoints: BTreeSet<usize>,
+    mode: Mode,
+    stack: Vec<String>,
+}
+
+impl Debugger {
+    /// If no breakpoints are given, the debugger pauses on the first statement.
+    pub fn new(filename: &str, source: String, breakpoints: &[usize]) -> Self {
+        Self {
+            filename: filename.into(),
+            lines: LineIndex::new(&source),
+            source,
+            breakpoints: breakpoints.iter().cloned().collect(),
+            mode: if breakpoints.is_empty() {
+                Mode::StepInto
+            } else {
+                Mode::Continue
+            },
+            stack: Vec::new(),
+        }
+    }
+
+    fn pause(&mut self, state: &mut State, line: usize) {
+        self.show_lines(line, 0);
+
+        loop {
+            eprint!("(schwift) ");
+            io::stderr().flush().ok();
+
+            let mut command = String::new();
+            match state.read_line(&mut command) {
+                Ok(0) | Err(_) => {
+                    // Nobody is listening anymore, so just let the program finish
+                    self.breakpoints.clear();
+                    self.mode = Mode::Continue;
+                    return;
+                }
+                Ok(_) => {}
+            }
+
+            let command = command.trim();
+            let (word, rest) = match command.find(char::is_whitespace) {
+                Some(idx) => (&command[..idx], command[idx..].trim()),
+                None => (command, ""),
+            };
+
+            let depth = self.stack.len();
+
+            match word {
+                "" => {}
+                "c" | "continue" => {
+                    self.mode = Mode::Continue;
+                    return;
+                }
+                "s" | "step" => {
+                    self.mode = Mode::StepInto;
+                    return;
+                }
+                "n" | "next" => {
+                    self.mode = Mode::StepOver(depth);
+                    return;
+                }
+                "o" | "out" => {
+                    self.mode = Mode::StepOut(depth);
+                    return;
+                }
+                "b" | "break" => match rest.parse() {
+                    Ok(line) => {
+                        self.breakpoints.insert(line);
+                        eprintln!("Breakpoint set at {}:{}", self.filename, line);
+                    }
+                    Err(_) => eprintln!("I need a line number, Morty"),
+                },
+                "d" | "delete" => match rest.parse() {
+                    Ok(line) => {
+                        self.breakpoints.remove(&line);
+                    }
+                    Err(_) => eprintln!("I need a line number, Morty"),
+                },
+                "p" | "print" => match grammar::expression(rest) {
+                    Ok(exp) => match exp.evaluate(state) {
+                        Ok(value) => eprintln!("{}", value),
+                        Err(e) => eprintln!("{}", e.kind()),
+                    },
+                    Err(e) => eprintln!("SYNTAX ERROR: expected {}", e.expected),
+                },
+                "v" | "vars" => {
+                    for (name, value) in state.variables() {
+                        eprintln!("{} = {}", name, value);
+                    }
+                }
+                "w" | "where" => {
+                    eprintln!("{}:{}", self.filename, line);
+                    for name in self.stack.iter().rev() {
+                        eprintln!("  in {}()", name);
+                    }
+                }
+                "l" | "list" => self.show_lines(line, 3),
+                "q" | "quit" => process::exit(0),
+                "h" | "help" => eprintln!("{}", HELP),
+                _ => match grammar::statement(command) {
+                    Ok(statement) => {
+                        if let Err(e) = state.execute(&statement) {
+                            eprintln!("{}", e.kind());
+                        }
+                    }
+                    Err(_) => eprintln!("I don't know how to {}, Morty. Try help.", word),
+                },
+            }
+        }
+    }
+
+    fn show_lines(&self, line: usize, context: usize) {
+        let first = line.saturating_sub(context).max(1);
+
+        for (idx, text) in self.source.lines().enumerate().skip(first - 1) {
+            let number = idx + 1;
+            if number > line + context {
+                break;
+            }
+
+            let marker = if number == line { "->" } else { "  " };
+            eprintln!("{} {}:{}\t{}", marker, self.filename, number, text);
+        }
+    }
+}
+
+impl Hook for Debugger {
+    fn statement(&mut self, state: &mut State, statement: &Statement) {
+        let line = self.lines.line(statement.start());
+        let depth = self.stack.len();
+
+        if self.breakpoints.contains(&line) || self.mode.stops_at(depth) {
+            self.pause(state, line);
+        }
+    }
+
+    fn enter_function(&mut self, name: &str) {
+        self.stack.push(name.into());
+    }
+
+    fn exit_function(&mut self, _name: &str) {
+        self.stack.pop();
+    }
+}
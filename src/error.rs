@@ -1,6 +1,10 @@
 use crate::{grammar, statement::Statement, value, Operator};
 use rand::{seq::SliceRandom, thread_rng};
-use std::{fmt::Write, io, process};
+use std::{
+    fmt::Write as _,
+    io::{self, Write as _},
+    process,
+};
 
 pub type SwResult<T> = Result<T, EitherError>;
 
@@ -46,8 +50,14 @@ pub enum ErrorKind {
     #[error("If you're going to start trying to construct sub-programs in your programs Morty, you'd better make sure you're careful!")]
     SyntaxError(grammar::ParseError),
 
+    #[error("Which kernel, Morty? You have to say where in {0} you mean.")]
+    MissingIndex(String),
+
     #[error("Y-you can't just keep asking for more, Morty! You want {index}, but your cob only has {len} kernels on it!")]
-    IndexOutOfBounds { len: usize, index: usize },
+    IndexOutOfBounds { len: usize, index: value::IntT },
+
+    #[error("A step of zero? You'd be slicing the same kernel forever, Morty!")]
+    ZeroStep,
 
     #[error("Looks like we're having a comm-burp-unications problem Morty")]
     IOError(#[from] io::Error),
@@ -65,6 +75,17 @@ pub enum ErrorKind {
     #[error("It's like apples and space worms Morty! You can't {2:?} a {0} and a {1}!")]
     InvalidBinaryExpression(value::Type, value::Type, Operator),
 
+    #[error(
+        "That number's too big for this dimension, Morty! {0:?} went past what an int can hold"
+    )]
+    Overflow(Operator),
+
+    #[error("You can't {0:?} by zero Morty! Not even in this dimension!")]
+    DivisionByZero(Operator),
+
+    #[error("You can't shift by {0} Morty, an int only has 64 bits!")]
+    InvalidShift(value::IntT),
+
     #[error("I'm confused Morty, a minute ago you said that {0} takes {1} paramaters, but you just tried to give it {2}. WHICH IS IT MORTY?")]
     InvalidArguments(String, usize, usize),
 
@@ -90,6 +111,31 @@ pub enum ErrorKind {
         "I told you how a Microverse works Morty. At what point exactly did you stop listening?"
     )]
     DylibReturnedNil,
+
+    #[error("Wubba lubba dub dub! That's not true Morty, and you said it would be.")]
+    AssertionFailed,
+
+    #[error("Your format string is a mess, Morty: {0}")]
+    InvalidFormat(String),
+
+    #[error("The program asked to exit with status {0}")]
+    Exit(i32),
+
+    #[error("You can't exit with {0} Morty, that's not a status any universe has heard of.")]
+    InvalidExitStatus(value::IntT),
+
+    #[error("Whoa, whoa, whoa Morty, this universe is sandboxed. You're not allowed to call {0} in here.")]
+    Sandboxed(String),
+
+    #[error("That's not JSON Morty, it's garbage! Line {line}, column {column}: {message}")]
+    InvalidJson {
+        line: usize,
+        column: usize,
+        message: String,
+    },
+
+    #[error("You can't turn {0} into JSON Morty, it doesn't work like that.")]
+    NotJson(String),
 }
 
 impl<T> From<T> for EitherError
@@ -101,6 +147,15 @@ where
     }
 }
 
+impl EitherError {
+    pub fn kind(&self) -> &ErrorKind {
+        match self {
+            EitherError::WithContext(e) => e.kind(),
+            EitherError::NoContext(kind) => kind,
+        }
+    }
+}
+
 pub trait ErrorKindExt<T> {
     fn with_error_ctx(self, stmt: &Statement) -> Result<T, ErrorWithContext>;
 }
@@ -134,7 +189,23 @@ impl PartialEq for ErrorKind {
             (IndexUnindexable(ref s), IndexUnindexable(ref o)) => s == o,
             (SyntaxError(ref s), SyntaxError(ref o)) => s == o,
             (UnknownVariable(ref s), UnknownVariable(ref o))
-            | (NoReturn(ref s), NoReturn(ref o)) => s == o,
+            | (NoReturn(ref s), NoReturn(ref o))
+            | (InvalidFormat(ref s), InvalidFormat(ref o))
+            | (Sandboxed(ref s), Sandboxed(ref o))
+            | (MissingIndex(ref s), MissingIndex(ref o))
+            | (NotJson(ref s), NotJson(ref o)) => s == o,
+            (
+                InvalidJson {
+                    line: sline,
+                    column: scolumn,
+                    ..
+                },
+                InvalidJson {
+                    line: oline,
+                    column: ocolumn,
+                    ..
+                },
+            ) => sline == oline && scolumn == ocolumn,
             (InvalidArguments(ref sn, ss1, ss2), InvalidArguments(ref on, os1, os2)) => {
                 sn == on && ss1 == os1 && ss2 == os2
             }
@@ -175,7 +246,15 @@ impl PartialEq for ErrorKind {
                 },
             ) => lib1 == lib2,
             (IncompatibleAbi(ver1), IncompatibleAbi(ver2)) => ver1 == ver2,
-            (DylibReturnedNil, DylibReturnedNil) => true,
+            (Overflow(ref so), Overflow(ref oo))
+            | (DivisionByZero(ref so), DivisionByZero(ref oo)) => so == oo,
+            (InvalidShift(s), InvalidShift(o)) | (InvalidExitStatus(s), InvalidExitStatus(o)) => {
+                s == o
+            }
+            (Exit(code1), Exit(code2)) => code1 == code2,
+            (DylibReturnedNil, DylibReturnedNil)
+            | (AssertionFailed, AssertionFailed)
+            | (ZeroStep, ZeroStep) => true,
             _ => false,
         }
     }
@@ -186,6 +265,14 @@ impl ErrorWithContext {
         Self { kind, place }
     }
 
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+
+    pub fn place(&self) -> &Statement {
+        &self.place
+    }
+
     pub fn full_panic_message(&self, filename: &str) -> String {
         let mut f = String::new();
 
@@ -217,7 +304,14 @@ impl ErrorWithContext {
         f
     }
 
+    /// Print the error and exit, or just exit with the program's chosen status if it called
+    /// `exit`.
     pub fn panic(&self, source: &str) {
+        if let ErrorKind::Exit(code) = self.kind {
+            io::stdout().flush().ok();
+            process::exit(code);
+        }
+
         println!("{}", self.full_panic_message(source));
         process::exit(1);
     }
@@ -12,13 +12,23 @@ pub enum Expression {
     Variable(String),
     OpExp(Box<Expression>, Operator, Box<Expression>),
     Value(Value),
-    ListIndex(String, Box<Expression>),
+    List(Vec<Expression>),
+    ListIndex(Box<Expression>, Box<Expression>),
+    Slice(Box<Expression>, Box<Slice>),
     ListLength(String),
     Not(Box<Expression>),
     Eval(Box<Expression>),
     FunctionCall(String, Vec<Expression>),
 }
 
+/// The `[start:end:step]` part of a slice, any of which can be left out.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Slice {
+    pub start: Option<Expression>,
+    pub end: Option<Expression>,
+    pub step: Option<Expression>,
+}
+
 impl<T> From<T> for Expression
 where
     T: Into<Value>,
@@ -39,7 +49,7 @@ impl Expression {
                     Operator::Add => left.add(&right),
                     Operator::Subtract => left.subtract(&right),
                     Operator::Multiply => left.multiply(&right),
-                    Operator::Divide => left.divide(&right),
+                    Operator::Divide => left.divide(&right, state.ieee_floats()),
                     Operator::Equality => Ok(left.equals(&right)),
                     Operator::LessThan => left.less_than(&right),
                     Operator::GreaterThan => left.greater_than(&right),
@@ -50,18 +60,34 @@ impl Expression {
                     Operator::And => left.and(&right),
                     Operator::Or => left.or(&right),
                     Operator::Modulus => left.modulus(&right),
+                    Operator::BitAnd => left.bit_and(&right),
+                    Operator::BitOr => left.bit_or(&right),
+                    Operator::BitXor => left.bit_xor(&right),
+                    Operator::Power => left.power(&right, state.ieee_floats()),
+                    Operator::FloorDivide => left.floor_divide(&right, state.ieee_floats()),
                 };
 
                 result.map(borrow::Cow::Owned)
             }
             Expression::Value(ref v) => Ok(borrow::Cow::Borrowed(v)),
-            Expression::ListIndex(ref var_name, ref e) => state.list_index(var_name, e),
+            Expression::List(ref items) => {
+                let mut list = Vec::with_capacity(items.len());
+                for item in items {
+                    list.push(item.evaluate(state)?.into_owned());
+                }
+
+                Ok(borrow::Cow::Owned(Value::List(list)))
+            }
+            Expression::ListIndex(ref list, ref e) => state.list_index(list, e),
+            Expression::Slice(ref list, ref slice) => {
+                state.slice(list, slice).map(borrow::Cow::Owned)
+            }
             Expression::Not(ref e) => e.evaluate(state)?.not().map(borrow::Cow::Owned),
             Expression::ListLength(ref var_name) => {
                 let value = state.get(var_name)?;
                 match *value {
                     Value::List(ref list) => Ok(borrow::Cow::Owned(Value::Int(list.len() as IntT))),
-                    Value::Str(ref s) => Ok(borrow::Cow::Owned(Value::Int(s.len() as IntT))),
+                    Value::Str(ref s) => Ok(borrow::Cow::Owned(Value::Int(s.char_len() as IntT))),
                     _ => Err(ErrorKind::IndexUnindexable(value.get_type()).into()),
                 }
             }
@@ -89,7 +115,7 @@ impl Expression {
         }
     }
 
-    pub fn try_bool(&self, state: &mut State) -> SwResult<bool> {
+    pub fn try_bool(&self, state: &State) -> SwResult<bool> {
         let value = self.evaluate(state)?;
         if let Value::Bool(x) = *value {
             Ok(x)
@@ -102,7 +128,7 @@ impl Expression {
         }
     }
 
-    pub fn try_int(&self, state: &mut State) -> SwResult<IntT> {
+    pub fn try_int(&self, state: &State) -> SwResult<IntT> {
         let value = self.evaluate(state)?;
         if let Value::Int(x) = *value {
             Ok(x)
@@ -166,7 +192,30 @@ impl Expression {
         S: Into<String>,
         E: Into<Expression>,
     {
-        Expression::ListIndex(name.into(), Box::new(index.into()))
+        Expression::index(Expression::variable(name), index)
+    }
+
+    pub fn index<L, E>(list: L, index: E) -> Expression
+    where
+        L: Into<Expression>,
+        E: Into<Expression>,
+    {
+        Expression::ListIndex(Box::new(list.into()), Box::new(index.into()))
+    }
+
+    pub fn slice<S>(
+        name: S,
+        start: Option<Expression>,
+        end: Option<Expression>,
+        step: Option<Expression>,
+    ) -> Expression
+    where
+        S: Into<String>,
+    {
+        Expression::Slice(
+            Box::new(Expression::variable(name)),
+            Box::new(Slice { start, end, step }),
+        )
     }
 
     pub fn value<V>(val: V) -> Expression
@@ -0,0 +1,198 @@
+use crate::{error::ErrorKind, value::Value};
+use std::{iter::Peekable, str::Chars};
+
+#[cfg(test)]
+mod test;
+
+enum Align {
+    Left,
+    Right,
+    Center,
+}
+
+/// What goes after the `:` in a placeholder, `[[fill]align][0][width][.precision]`.
+#[derive(Default)]
+struct Spec {
+    fill: Option<char>,
+    align: Option<Align>,
+    zero: bool,
+    width: usize,
+    precision: Option<usize>,
+}
+
+fn align(c: char) -> Option<Align> {
+    match c {
+        '<' => Some(Align::Left),
+        '>' => Some(Align::Right),
+        '^' => Some(Align::Center),
+        _ => None,
+    }
+}
+
+fn invalid<T>(message: String) -> Result<T, ErrorKind> {
+    Err(ErrorKind::InvalidFormat(message))
+}
+
+fn number(chars: &mut Peekable<Chars>) -> Option<usize> {
+    let mut digits = String::new();
+
+    while let Some(c) = chars.peek().filter(|c| c.is_ascii_digit()) {
+        digits.push(*c);
+        chars.next();
+    }
+
+    digits.parse().ok()
+}
+
+impl Spec {
+    fn parse(spec: &str) -> Result<Self, ErrorKind> {
+        let mut result = Self::default();
+
+        let mut lookahead = spec.chars();
+        if let (Some(fill), Some(a)) = (lookahead.next(), lookahead.next().and_then(align)) {
+            result.fill = Some(fill);
+            result.align = Some(a);
+        }
+
+        let mut chars = spec.chars().peekable();
+        if result.fill.is_some() {
+            chars.next();
+            chars.next();
+        } else if let Some(a) = chars.peek().cloned().and_then(align) {
+            result.align = Some(a);
+            chars.next();
+        }
+
+        if chars.peek() == Some(&'0') {
+            result.zero = true;
+            chars.next();
+        }
+
+        result.width = number(&mut chars).unwrap_or_default();
+
+        if chars.peek() == Some(&'.') {
+            chars.next();
+            match number(&mut chars) {
+                Some(precision) => result.precision = Some(precision),
+                None => return invalid(format!("{{:{}}} needs a number after the .", spec)),
+            }
+        }
+
+        match chars.next() {
+            Some(c) => invalid(format!("I don't know what {} means in {{:{}}}", c, spec)),
+            None => Ok(result),
+        }
+    }
+
+    fn apply(&self, value: &Value) -> String {
+        let numeric = matches!(value, Value::Int(_) | Value::Float(_));
+
+        let text = match (value, self.precision) {
+            (Value::Float(f), Some(precision)) => format!("{:.*}", precision, f),
+            (Value::Int(_), _) | (_, None) => value.to_string(),
+            (_, Some(precision)) => value.to_string().chars().take(precision).collect(),
+        };
+
+        let len = text.chars().count();
+        if len >= self.width {
+            return text;
+        }
+        let padding = self.width - len;
+
+        // Zeros go between the sign and the digits, so they only make sense for numbers
+        if self.zero && self.fill.is_none() && self.align.is_none() && numeric {
+            let (sign, digits) = match text.strip_prefix('-') {
+                Some(digits) => ("-", digits),
+                None => ("", text.as_str()),
+            };
+
+            return format!("{}{}{}", sign, "0".repeat(padding), digits);
+        }
+
+        let fill = self.fill.unwrap_or(if self.zero { '0' } else { ' ' });
+        let fill = |count| fill.to_string().repeat(count);
+
+        let align = match self.align {
+            Some(ref align) => align,
+            None if numeric || self.zero => &Align::Right,
+            None => &Align::Left,
+        };
+
+        match align {
+            Align::Left => text + &fill(padding),
+            Align::Right => fill(padding) + &text,
+            Align::Center => fill(padding / 2) + &text + &fill(padding - padding / 2),
+        }
+    }
+}
+
+/// Fill in the `{}` placeholders in `template` with `args`, in order.
+///
+/// Placeholders can be given a spec after a colon like Rust's, so `{:>8}` right-aligns in 8
+/// characters, `{:*^8}` centers with stars, `{:08}` pads a number with zeros and `{:.2}` shows a
+/// float with 2 decimal places or cuts anything else down to 2 characters. `{{` and `}}` are a
+/// literal `{` and `}`.
+pub fn format(template: &str, args: &[Value]) -> Result<String, ErrorKind> {
+    let mut result = String::new();
+    let mut args = args.iter();
+    let mut used = 0;
+    let mut chars = template.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.as_str().starts_with('{') => {
+                chars.next();
+                result.push('{');
+            }
+            '}' if chars.as_str().starts_with('}') => {
+                chars.next();
+                result.push('}');
+            }
+            '{' => {
+                let rest = chars.as_str();
+                let end = match rest.find('}') {
+                    Some(end) => end,
+                    None => return invalid(format!("{{{} is missing a }}", rest)),
+                };
+
+                let placeholder = &rest[..end];
+                let spec = match placeholder {
+                    "" => Spec::default(),
+                    _ => match placeholder.strip_prefix(':') {
+                        Some(spec) => Spec::parse(spec)?,
+                        None => {
+                            return invalid(format!("{{{}}} should start with a :", placeholder))
+                        }
+                    },
+                };
+
+                let value = match args.next() {
+                    Some(value) => value,
+                    None => {
+                        return invalid(format!(
+                            "there are more placeholders than the {} values you gave me",
+                            used
+                        ))
+                    }
+                };
+                used += 1;
+
+                result.push_str(&spec.apply(value));
+                chars = rest[end + 1..].chars();
+            }
+            '}' => return invalid("there's a } without a {, use }} if you meant it".into()),
+            c => result.push(c),
+        }
+    }
+
+    let left_over = args.count();
+    if left_over > 0 {
+        return invalid(format!(
+            "you gave me {} values but there are only {} placeholders",
+            used + left_over,
+            used
+        ));
+    }
+
+    Ok(result)
+}
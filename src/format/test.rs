@@ -0,0 +1,57 @@
+use super::format;
+use crate::{error::ErrorKind, value::Value};
+
+#[test]
+fn test_placeholders() {
+    let args = [Value::new("Rick"), Value::new(70), Value::new(true)];
+    assert_eq!(
+        format("{} is {} years old: {}", &args).unwrap(),
+        "Rick is 70 years old: rick"
+    );
+}
+
+#[test]
+fn test_escaped_braces() {
+    assert_eq!(format("{{}} {{{}}}", &[Value::new(1)]).unwrap(), "{} {1}");
+}
+
+#[test]
+fn test_width_and_alignment() {
+    assert_eq!(format("[{:5}]", &[Value::new("ab")]).unwrap(), "[ab   ]");
+    assert_eq!(format("[{:5}]", &[Value::new(42)]).unwrap(), "[   42]");
+    assert_eq!(format("[{:>5}]", &[Value::new("ab")]).unwrap(), "[   ab]");
+    assert_eq!(format("[{:<5}]", &[Value::new(42)]).unwrap(), "[42   ]");
+    assert_eq!(format("[{:^6}]", &[Value::new("ab")]).unwrap(), "[  ab  ]");
+    assert_eq!(format("[{:*^5}]", &[Value::new("ab")]).unwrap(), "[*ab**]");
+    assert_eq!(format("[{:1}]", &[Value::new("long")]).unwrap(), "[long]");
+}
+
+#[test]
+fn test_zero_padding() {
+    assert_eq!(format("{:05}", &[Value::new(42)]).unwrap(), "00042");
+    assert_eq!(format("{:05}", &[Value::new(-42)]).unwrap(), "-0042");
+    assert_eq!(
+        format("{:08.3}", &[Value::new(-1.23456)]).unwrap(),
+        "-001.235"
+    );
+}
+
+#[test]
+fn test_precision() {
+    assert_eq!(format("{:.2}", &[Value::new(1.23456)]).unwrap(), "1.23");
+    assert_eq!(format("{:8.1}", &[Value::new(2.0)]).unwrap(), "     2.0");
+    assert_eq!(format("{:.3}", &[Value::new("squanch")]).unwrap(), "squ");
+    assert_eq!(format("{:.3}", &[Value::new(12345)]).unwrap(), "12345");
+}
+
+#[test]
+fn test_invalid_formats() {
+    let one = [Value::new(1)];
+
+    for template in &["{} {}", "", "{", "}", "{:x}", "{:.}", "{0}"] {
+        match format(template, &one) {
+            Err(ErrorKind::InvalidFormat(_)) => {}
+            result => panic!("{:?} gave {:?}", template, result),
+        }
+    }
+}
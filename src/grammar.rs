@@ -1,30 +1,51 @@
-use crate::expression::Expression;
+use crate::expression::{Expression, Slice};
 use crate::statement::{Statement, StatementKind};
-use crate::value::{string_parse, FloatT, IntT, Value};
+use crate::value::{FloatT, IntT, Value};
 use crate::Operator;
 
 pub use self::grammar::*;
 
+enum Index {
+    At(Expression),
+    Slice(Slice),
+}
+
 pub type ParseError = peg::error::ParseError<peg_runtime::str::LineCol>;
 
 peg::parser! {grammar grammar() for str {
 
-    rule string_inquotes() -> String
-        = s:$((!['"'][_])*) { string_parse(s) }
+    rule hex_digit() = ['0'..='9' | 'a'..='f' | 'A'..='F']
+
+    rule escape() -> char
+        = "n" { '\n' }
+        / "t" { '\t' }
+        / "r" { '\r' }
+        / "0" { '\0' }
+        / "\"" { '"' }
+        / "\\" { '\\' }
+        / "x" h:$(['0'..='7'] hex_digit()) { u8::from_str_radix(h, 16).unwrap() as char }
+        / "u{" h:$(hex_digit()*<1,6>) "}" {?
+            u32::from_str_radix(h, 16).ok().and_then(std::char::from_u32).ok_or("a unicode scalar value")
+        }
+        / expected!("an escape sequence")
+
+    rule string_char() -> char
+        = "\\" c:escape() { c }
+        / !['"' | '\\'] c:$([_]) { c.chars().next().unwrap() }
 
     rule int() -> IntT
-        = n:$("-"? ['0'..='9']+) { n.parse::<IntT>().unwrap() }
+        = n:$("-"? ['0'..='9']+) {? n.parse::<IntT>().or(Err("an integer that fits in 64 bits")) }
 
     rule float() -> FloatT
         = n:$(['0'..='9']+ "." ['0'..='9']+) { n.parse::<FloatT>().unwrap() }
 
     rule string() -> String
-        = "\"" s:string_inquotes() "\"" { s }
+        = "\"" s:string_char()* "\"" { s.into_iter().collect() }
 
     pub rule value() -> Value
         = f:float() { Value::Float(f) }
         / i:int() { Value::Int(i) }
-        / s:string() { Value::Str(s) }
+        / s:string() { Value::Str(s.into()) }
         / "rick" { Value::Bool(true) }
         / "morty" { Value::Bool(false) }
 
@@ -34,10 +55,15 @@ peg::parser! {grammar grammar() for str {
     pub rule operator() -> Operator
         = "+" { Operator::Add }
         / "-" { Operator::Subtract }
+        / "**" { Operator::Power }
         / "*" { Operator::Multiply }
+        / "//" { Operator::FloorDivide }
         / "/" { Operator::Divide }
         / "==" { Operator::Equality }
         / "%" { Operator::Modulus }
+        / "&" { Operator::BitAnd }
+        / "|" { Operator::BitOr }
+        / "^" { Operator::BitXor }
         / "moresquanch" { Operator::GreaterThanEqual }
         / "lesssquanch" { Operator::LessThanEqual }
         / "more" { Operator::GreaterThan }
@@ -72,18 +98,23 @@ peg::parser! {grammar grammar() for str {
         = "(" is:identifier() ** comma() ")" { is }
 
     pub rule statement_kind() -> StatementKind
-        = "squanch" WS() i:identifier() ws() "[" ws() e:expression() ws() "]" { StatementKind::ListDelete(i, e) }
+        = "squanch" WS() i:identifier() ws() p:path() { StatementKind::ListDelete(i, p) }
         / i:identifier() WS() "on a cob" { StatementKind::ListNew(i) }
-        / i:identifier() WS() "assimilate" WS() e:expression() { StatementKind::ListAppend(i, e) }
-        / i:identifier() ws() "[" ws() v:expression() ws() "]" WS() "squanch" WS() e:expression() { StatementKind::ListAssign(i, v, e) }
+        / i:identifier() p:(ws() p:path() { p })? WS() "assimilate" WS() e:expression() { StatementKind::ListAppend(i, p.unwrap_or_default(), e) }
+        / i:identifier() ws() p:path() WS() "squanch" WS() e:expression() { StatementKind::ListAssign(i, p, e) }
         / n:identifier() ws() p:params() ws() b:block() { StatementKind::Function(n, p, b) }
         / "squanch" WS() i:identifier() { StatementKind::Delete(i) }
         / i:identifier() WS() "squanch" WS() e:expression() { StatementKind::Assignment(i, e) }
         / "show me what you got!" WS() e:expression() { StatementKind::PrintNoNl(e) }
         / "show me what you got" WS() e:expression() { StatementKind::Print(e) }
+        / "existence is pain!" WS() e:expression() { StatementKind::PrintErrNoNl(e) }
+        / "existence is pain" WS() e:expression() { StatementKind::PrintErr(e) }
+        / "wubba lubba dub dub" WS() e:expression() { StatementKind::Assert(e) }
         / "if" WS() e:expression() WS() i_bod:block() ws() "else" WS() e_bod:block() { StatementKind::If(e, i_bod, Option::Some(e_bod)) }
         / "if" WS() e:expression() WS() s:block() { StatementKind::If(e, s, Option::None) }
         / "while" WS() e:expression() WS() b:block() { StatementKind::While(e, b) }
+        / "portal gun" WS() "raw" WS() i:identifier() { StatementKind::InputRaw(i) }
+        / "portal gun" WS() "all" WS() i:identifier() { StatementKind::InputAll(i) }
         / "portal gun" WS() i:identifier() { StatementKind::Input(i) }
         / "normal plan" ws() try_block:block() ws() "plan for failure" ws() catch:block() { StatementKind::Catch(try_block, catch) }
         / i:identifier() a:args() { StatementKind::FunctionCall(i, a) }
@@ -93,7 +124,23 @@ peg::parser! {grammar grammar() for str {
     pub rule statement() -> Statement
         = start:position!() s:statement_kind() end:position!() { Statement::new(s, start, end) }
 
+    /// The indexes in `m[i][j]`, for statements that change a nested list
+    rule path() -> Vec<Expression>
+        = ("[" ws() e:expression() ws() "]" { e }) ++ ws()
+
+    rule index() -> Index
+        = "[" ws() s:slice() ws() "]" { Index::Slice(s) }
+        / "[" ws() e:expression() ws() "]" { Index::At(e) }
+
     pub rule expression() -> Expression
+        = e:expression0() indexes:(ws() i:index() { i })* {
+            indexes.into_iter().fold(e, |list, index| match index {
+                Index::At(e) => Expression::ListIndex(Box::new(list), Box::new(e)),
+                Index::Slice(s) => Expression::Slice(Box::new(list), Box::new(s)),
+            })
+        }
+
+    rule expression0() -> Expression
         = "{" ws() e:expression() ws() "}" { Expression::Eval(Box::new(e)) }
         / "(" ws() e1:expression() ws() o:operator() ws() e2:expression() ws() ")" { Expression::OpExp(Box::new(e1), o, Box::new(e2)) }
         / "(" ws() e:expression1() ws() ")" { e }
@@ -102,10 +149,15 @@ peg::parser! {grammar grammar() for str {
     pub rule args() -> Vec<Expression>
         = "(" exprs:expression() ** comma() ")" { exprs }
 
+    rule slice() -> Slice
+        = start:expression()? ws() ":" ws() end:expression()? ws() step:(":" ws() e:expression()? { e })? {
+            Slice { start, end, step: step.flatten() }
+        }
+
     rule expression1() -> Expression
-        = i:identifier() ws() "[" ws() e:expression() ws() "]" { Expression::ListIndex(i, Box::new(e)) }
-        / i:identifier() a:args() { Expression::FunctionCall(i, a) }
+        = i:identifier() a:args() { Expression::FunctionCall(i, a) }
         / v:value() { Expression::Value(v) }
+        / "[" ws() items:expression() ** comma() ws() "]" { Expression::List(items) }
         / i:identifier() WS() "squanch" { Expression::ListLength(i) }
         / i:identifier() { Expression::Variable(i) }
         / "!" ws() e:expression() { Expression::Not(Box::new(e)) }
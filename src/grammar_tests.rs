@@ -1,5 +1,5 @@
 use crate::{
-    expression::Expression as Exp,
+    expression::{Expression as Exp, Slice},
     grammar,
     statement::{Statement, StatementKind as Kind},
     value::Value,
@@ -16,12 +16,56 @@ fn test_int() {
     assert_eq!(l, Value::new(3))
 }
 
+#[test]
+fn test_int_limits() {
+    let l = grammar::value("-9223372036854775808").unwrap();
+    assert_eq!(l, Value::new(i64::MIN));
+
+    let e = grammar::value("9223372036854775808").unwrap_err();
+    assert!(e
+        .expected
+        .to_string()
+        .contains("an integer that fits in 64 bits"));
+}
+
 #[test]
 fn test_string() {
     let l = grammar::value("\"hello!\"").unwrap();
     assert_eq!(l, Value::new("hello!"))
 }
 
+#[test]
+fn test_string_escapes() {
+    let l = grammar::value(r#""a\"b\\c\n\t\r\0""#).unwrap();
+    assert_eq!(l, Value::new("a\"b\\c\n\t\r\0"));
+
+    let l = grammar::value(r#""\x41\x7f\u{1F952}\u{e9}""#).unwrap();
+    assert_eq!(l, Value::new("A\x7f\u{1F952}\u{e9}"));
+
+    // The character before an escape used to be eaten along with it
+    let l = grammar::value(r#""wubba\n""#).unwrap();
+    assert_eq!(l, Value::new("wubba\n"));
+}
+
+#[test]
+fn test_invalid_string_escapes() {
+    for code in &[
+        r#""\q""#,
+        r#""\x80""#,
+        r#""\x4""#,
+        r#""\u{}""#,
+        r#""\u{D800}""#,
+        r#""\u{1234567}""#,
+        r#""\""#,
+    ] {
+        assert!(grammar::value(code).is_err(), "{} should not parse", code);
+    }
+
+    let e = grammar::file("x squanch \"ok\\q\"\n").unwrap_err();
+    assert_eq!(e.location.column, 15);
+    assert!(e.expected.to_string().contains("an escape sequence"));
+}
+
 #[test]
 fn test_expression_string() {
     let l = grammar::expression("\"hello!\"").unwrap();
@@ -52,12 +96,58 @@ fn test_list_assign() {
     assert_eq!(l, Kind::list_assign("foobar", 30, 10));
 }
 
+#[test]
+fn test_nested_list_statements() {
+    let l = grammar::statement_kind("m[i][(j + 1)] squanch 0").unwrap();
+    assert_eq!(
+        l,
+        Kind::ListAssign(
+            "m".into(),
+            vec![
+                Exp::variable("i"),
+                Exp::operator(Exp::variable("j"), Op::Add, 1)
+            ],
+            Exp::new(0)
+        )
+    );
+
+    let l = grammar::statement_kind("m[0] [1] assimilate 2").unwrap();
+    assert_eq!(
+        l,
+        Kind::ListAppend("m".into(), vec![Exp::new(0), Exp::new(1)], Exp::new(2))
+    );
+
+    let l = grammar::statement_kind("squanch m[0][-1]").unwrap();
+    assert_eq!(
+        l,
+        Kind::ListDelete("m".into(), vec![Exp::new(0), Exp::new(-1)])
+    );
+}
+
 #[test]
 fn test_printing() {
     let l = grammar::statement_kind("show me what you got \"Hello\"").unwrap();
     assert_eq!(l, Kind::print("Hello"));
 }
 
+#[test]
+fn test_print_err() {
+    let l = grammar::statement_kind("existence is pain \"oh no\"").unwrap();
+    assert_eq!(l, Kind::print_err("oh no"));
+
+    let l = grammar::statement_kind("existence is pain! x").unwrap();
+    assert_eq!(l, Kind::PrintErrNoNl(Exp::variable("x")));
+}
+
+#[test]
+fn test_assert() {
+    let l = grammar::statement_kind("wubba lubba dub dub (x == 1)").unwrap();
+    assert_eq!(
+        l,
+        Kind::assert(Exp::operator(Exp::variable("x"), Op::Equality, 1))
+    );
+}
+
 #[test]
 fn test_list_length() {
     let l = grammar::expression("apple squanch").unwrap();
@@ -142,6 +232,15 @@ show me what you got 10
 fn test_input() {
     let l = grammar::statement_kind(r"portal gun x").unwrap();
     assert_eq!(l, Kind::input("x"));
+
+    let l = grammar::statement_kind(r"portal gun raw x").unwrap();
+    assert_eq!(l, Kind::InputRaw("x".into()));
+
+    let l = grammar::statement_kind(r"portal gun all x").unwrap();
+    assert_eq!(l, Kind::InputAll("x".into()));
+
+    let l = grammar::statement_kind(r"portal gun raw").unwrap();
+    assert_eq!(l, Kind::input("raw"));
 }
 
 #[test]
@@ -159,6 +258,86 @@ fn test_index_and_addition() {
     assert_eq!(l, Exp::operator(Exp::list_index("x", 10), Op::Add, 30));
 }
 
+#[test]
+fn test_slice() {
+    let l = grammar::expression("x[1:-1]").unwrap();
+    assert_eq!(
+        l,
+        Exp::slice("x", Some(Exp::new(1)), Some(Exp::new(-1)), None)
+    );
+
+    let l = grammar::expression("x[ : (n + 1) : 2 ]").unwrap();
+    assert_eq!(
+        l,
+        Exp::slice(
+            "x",
+            None,
+            Some(Exp::operator(Exp::variable("n"), Op::Add, 1)),
+            Some(Exp::new(2))
+        )
+    );
+
+    let l = grammar::expression("x[::-1]").unwrap();
+    assert_eq!(l, Exp::slice("x", None, None, Some(Exp::new(-1))));
+
+    let l = grammar::expression("x[:]").unwrap();
+    assert_eq!(l, Exp::slice("x", None, None, None));
+}
+
+#[test]
+fn test_chained_indexes() {
+    let l = grammar::expression("m[1][2]").unwrap();
+    assert_eq!(l, Exp::index(Exp::list_index("m", 1), 2));
+
+    let l = grammar::expression("matrix(4, 10)[0][1:]").unwrap();
+    assert_eq!(
+        l,
+        Exp::Slice(
+            Box::new(Exp::index(
+                Exp::FunctionCall("matrix".into(), vec![Exp::new(4), Exp::new(10)]),
+                0
+            )),
+            Box::new(Slice {
+                start: Some(Exp::new(1)),
+                end: None,
+                step: None,
+            })
+        )
+    );
+
+    let l = grammar::expression("(a + b)[0]").unwrap();
+    assert_eq!(
+        l,
+        Exp::index(
+            Exp::operator(Exp::variable("a"), Op::Add, Exp::variable("b")),
+            0
+        )
+    );
+}
+
+#[test]
+fn test_list_literals() {
+    let l = grammar::expression("[]").unwrap();
+    assert_eq!(l, Exp::List(vec![]));
+
+    let l = grammar::expression("[1, \"two\", [x, []], (x + 1)]").unwrap();
+    assert_eq!(
+        l,
+        Exp::List(vec![
+            Exp::new(1),
+            Exp::new("two"),
+            Exp::List(vec![Exp::variable("x"), Exp::List(vec![])]),
+            Exp::operator(Exp::variable("x"), Op::Add, 1),
+        ])
+    );
+
+    let l = grammar::expression("[ 1,2 ][0]").unwrap();
+    assert_eq!(l, Exp::index(Exp::List(vec![Exp::new(1), Exp::new(2)]), 0));
+
+    let l = grammar::statement_kind("x squanch []").unwrap();
+    assert_eq!(l, Kind::assignment("x", Exp::List(vec![])));
+}
+
 #[test]
 fn test_list_deletion() {
     let l = grammar::statement_kind(r"squanch x[10]").unwrap();
@@ -261,6 +440,27 @@ fn test_multiple_operator_expressions_parenthesis() {
     );
 }
 
+#[test]
+fn test_integer_operators() {
+    for (code, op) in &[
+        ("(x ** y)", Op::Power),
+        ("(x * y)", Op::Multiply),
+        ("(x // y)", Op::FloorDivide),
+        ("(x / y)", Op::Divide),
+        ("(x & y)", Op::BitAnd),
+        ("(x | y)", Op::BitOr),
+        ("(x ^ y)", Op::BitXor),
+    ] {
+        let l = grammar::expression(code).unwrap();
+        assert_eq!(
+            l,
+            Exp::operator(Exp::variable("x"), op.clone(), Exp::variable("y")),
+            "{}",
+            code
+        );
+    }
+}
+
 #[test]
 fn test_eval_static_string() {
     let l = grammar::expression(r#"{"(1 + 3)"}"#).unwrap();
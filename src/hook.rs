@@ -0,0 +1,36 @@
+use crate::{state::State, statement::Statement};
+use std::{cell::RefCell, rc::Rc};
+
+/// Something that wants to watch a program while it runs, like a debugger or a profiler.
+///
+/// A hook only sees the code that ran after it was installed with `State::set_hook`. Calls to
+/// functions that were defined before then, like the builtins, are treated as a single step.
+pub trait Hook {
+    /// Called right before each statement is executed.
+    fn statement(&mut self, _state: &mut State, _statement: &Statement) {}
+
+    fn enter_function(&mut self, _name: &str) {}
+
+    fn exit_function(&mut self, _name: &str) {}
+}
+
+/// Lets more than one hook watch the same program.
+impl Hook for Vec<Rc<RefCell<dyn Hook>>> {
+    fn statement(&mut self, state: &mut State, statement: &Statement) {
+        for hook in self.iter() {
+            hook.borrow_mut().statement(state, statement);
+        }
+    }
+
+    fn enter_function(&mut self, name: &str) {
+        for hook in self.iter() {
+            hook.borrow_mut().enter_function(name);
+        }
+    }
+
+    fn exit_function(&mut self, name: &str) {
+        for hook in self.iter() {
+            hook.borrow_mut().exit_function(name);
+        }
+    }
+}
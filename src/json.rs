@@ -0,0 +1,64 @@
+use crate::{
+    error::ErrorKind,
+    value::{FloatT, Value},
+};
+use serde_json::{Number, Value as Json};
+
+/// Turn JSON text into a value. Arrays become lists, objects become lists of `[key, value]`
+/// pairs since there's no map type, and `null` becomes `morty`.
+pub fn parse(text: &str) -> Result<Value, ErrorKind> {
+    let json: Json = serde_json::from_str(text).map_err(|e| {
+        // The position is already in the error, so it doesn't need to be in the message too
+        let position = format!(" at line {} column {}", e.line(), e.column());
+
+        ErrorKind::InvalidJson {
+            line: e.line(),
+            column: e.column(),
+            message: e.to_string().trim_end_matches(&position).to_string(),
+        }
+    })?;
+
+    Ok(from_json(json))
+}
+
+fn from_json(json: Json) -> Value {
+    match json {
+        Json::Null => Value::Bool(false),
+        Json::Bool(b) => Value::Bool(b),
+        Json::Number(n) => match n.as_i64() {
+            Some(i) => Value::Int(i),
+            None => Value::Float(n.as_f64().unwrap_or(FloatT::NAN)),
+        },
+        Json::String(s) => Value::Str(s.into()),
+        Json::Array(values) => Value::List(values.into_iter().map(from_json).collect()),
+        Json::Object(map) => Value::List(
+            map.into_iter()
+                .map(|(key, value)| Value::List(vec![Value::Str(key.into()), from_json(value)]))
+                .collect(),
+        ),
+    }
+}
+
+/// Turn a value into JSON text. Lists always become arrays, even if they look like the pairs
+/// that `parse` makes out of objects.
+pub fn serialize(value: &Value) -> Result<String, ErrorKind> {
+    Ok(to_json(value)?.to_string())
+}
+
+fn to_json(value: &Value) -> Result<Json, ErrorKind> {
+    Ok(match *value {
+        Value::Str(ref s) => Json::String(s.to_string()),
+        Value::Int(i) => Json::Number(i.into()),
+        Value::Float(f) => match Number::from_f64(f) {
+            Some(n) => Json::Number(n),
+            None => return Err(ErrorKind::NotJson(value.to_string())),
+        },
+        Value::Bool(b) => Json::Bool(b),
+        Value::List(ref values) => {
+            Json::Array(values.iter().map(to_json).collect::<Result<Vec<_>, _>>()?)
+        }
+        Value::Function(..) | Value::NativeFunction(_) => {
+            return Err(ErrorKind::NotJson(value.get_type().to_string()))
+        }
+    })
+}
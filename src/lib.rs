@@ -1,19 +1,39 @@
-use std::{fs::File, io::prelude::*};
+use std::{
+    cell::RefCell,
+    fs::{self, File},
+    io::{self, prelude::*},
+    path::Path,
+    rc::Rc,
+};
 
 mod grammar;
 
 #[cfg(test)]
 mod grammar_tests;
 
+pub mod builtins;
+pub mod coverage;
+pub mod dap;
+pub mod debugger;
 pub mod error;
 pub mod expression;
+mod format;
+pub mod hook;
+mod json;
+pub mod lint;
+pub mod lsp;
+pub mod profile;
+mod rpc;
 pub mod state;
 pub mod statement;
+pub mod string;
+pub mod testing;
+pub mod trace;
 mod util;
 pub mod value;
 mod vec_map;
 
-use crate::{state::*, statement::*};
+use crate::{hook::Hook, state::*, statement::*};
 
 const BUILTINS_FILE: &str = "builtins.y";
 const BUILTINS: &str = include_str!("builtins.y");
@@ -35,7 +55,7 @@ macro_rules! plugin_fn {
 }
 
 #[no_mangle]
-pub static LIBSCHWIFT_ABI_COMPAT: u32 = 2;
+pub static LIBSCHWIFT_ABI_COMPAT: u32 = 4;
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum Operator {
@@ -53,6 +73,11 @@ pub enum Operator {
     And,
     Or,
     Modulus,
+    BitAnd,
+    BitOr,
+    BitXor,
+    Power,
+    FloorDivide,
 }
 
 fn get_line<'a>(file: &'a str, err: &grammar::ParseError) -> &'a str {
@@ -93,7 +118,7 @@ fn place_carat(err: &grammar::ParseError) -> String {
     s
 }
 
-pub fn compile(filename: &str) -> Vec<Statement> {
+fn read_source(filename: &str) -> String {
     let mut f = match File::open(filename) {
         Result::Ok(i) => i,
         Result::Err(_) => panic!("Failed to open file {}", filename),
@@ -104,7 +129,11 @@ pub fn compile(filename: &str) -> Vec<Statement> {
         Result::Err(_) => panic!("Failed to read file {}", filename),
     };
 
-    parse_str(&s, filename)
+    s
+}
+
+pub fn compile(filename: &str) -> Vec<Statement> {
+    parse_str(&read_source(filename), filename)
 }
 
 fn parse_str(source: &str, filename: &str) -> Vec<Statement> {
@@ -123,24 +152,248 @@ fn parse_str(source: &str, filename: &str) -> Vec<Statement> {
     }
 }
 
-pub fn run_program(filename: &str, args: &[&str]) {
+/// Parse a whole program, ready to be given to `State::run`.
+pub fn parse(source: &str) -> Result<Vec<Statement>, error::ErrorKind> {
+    grammar::file(source).map_err(error::ErrorKind::SyntaxError)
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RunOptions {
+    /// Don't let the program run other programs, load microverses or write to files
+    pub sandbox: bool,
+
+    /// Let floats be divided by zero, giving infinity or NaN instead of an error
+    pub ieee_floats: bool,
+}
+
+/// Run a program.
+pub fn run_program(filename: &str, args: &[&str], options: &RunOptions) {
+    if let Err(e) = run_program_with_hook(filename, args, None, options) {
+        e.panic(filename);
+    }
+}
+
+/// Run a program with the debugger attached, pausing at the given lines.
+pub fn debug_program(filename: &str, args: &[&str], breakpoints: &[usize], options: &RunOptions) {
+    let debugger = debugger::Debugger::new(filename, read_source(filename), breakpoints);
+
+    let debugger = Rc::new(RefCell::new(debugger));
+
+    if let Err(e) = run_program_with_hook(filename, args, Some(debugger), options) {
+        e.panic(filename);
+    }
+}
+
+pub struct TraceOptions<'a> {
+    /// Print every statement to stderr as it runs
+    pub trace: bool,
+
+    /// Print how often each function and line ran, and how long the functions took
+    pub profile: bool,
+
+    /// Write the profile to this file as folded stacks, which flamegraph tools can read
+    pub folded: Option<&'a str>,
+
+    /// Write an lcov report of which lines, functions and branches ran to this file
+    pub coverage: Option<&'a str>,
+}
+
+/// Run a program while tracing, profiling or measuring coverage. Reports are written even if the
+/// program fails.
+pub fn trace_program(
+    filename: &str,
+    args: &[&str],
+    options: &TraceOptions,
+    run_options: &RunOptions,
+) {
+    let source = read_source(filename);
+
+    let mut hooks: Vec<Rc<RefCell<dyn Hook>>> = Vec::new();
+
+    if options.trace {
+        let tracer = trace::Tracer::new(filename, &source, io::stderr());
+        hooks.push(Rc::new(RefCell::new(tracer)));
+    }
+
+    let profiler = Rc::new(RefCell::new(profile::Profiler::new(filename, &source)));
+    if options.profile || options.folded.is_some() {
+        hooks.push(profiler.clone());
+    }
+
+    let coverage = Rc::new(RefCell::new(coverage::Coverage::new(filename, &source)));
+    if options.coverage.is_some() {
+        hooks.push(coverage.clone());
+    }
+
+    let hooks = Rc::new(RefCell::new(hooks));
+    let result = run_program_with_hook(filename, args, Some(hooks), run_options);
+
+    let mut profiler = profiler.borrow_mut();
+    profiler.finish();
+
+    if options.profile {
+        profiler.report(&mut io::stderr()).unwrap();
+    }
+
+    if let Some(path) = options.folded {
+        let written = File::create(path).and_then(|mut f| profiler.write_folded(&mut f));
+        if let Err(e) = written {
+            eprintln!("Failed to write profile to {}: {}", path, e);
+        }
+    }
+
+    if let Some(path) = options.coverage {
+        let written = File::create(path).and_then(|mut f| coverage.borrow().write_lcov(&mut f));
+        if let Err(e) = written {
+            eprintln!("Failed to write coverage to {}: {}", path, e);
+        }
+    }
+
+    if let Err(e) = result {
+        e.panic(filename);
+    }
+}
+
+/// Run a program with `hook` watching it, after the builtins have been loaded.
+pub fn run_program_with_hook(
+    filename: &str,
+    args: &[&str],
+    hook: Option<Rc<RefCell<dyn Hook>>>,
+    options: &RunOptions,
+) -> Result<(), error::ErrorWithContext> {
     let mut s = State::new();
 
+    if options.sandbox {
+        s.sandbox();
+    }
+    s.set_ieee_floats(options.ieee_floats);
+
     s.parse_args(args);
+    s.set_program(filename);
 
-    let tokens = parse_str(BUILTINS, BUILTINS_FILE);
+    builtins::load(&mut s);
 
-    match s.run(&tokens) {
-        Ok(()) => {}
-        Err(e) => e.panic(BUILTINS_FILE),
+    if let Some(hook) = hook {
+        s.set_hook(hook);
     }
 
     let tokens = compile(filename);
 
-    match s.run(&tokens) {
-        Ok(()) => {}
-        Err(e) => e.panic(filename),
-    }
+    let result = s.run(&tokens);
 
     std::mem::forget(s);
+
+    result
+}
+
+/// Print every lint found in the given file, returning how many there were.
+pub fn lint_program(filename: &str) -> usize {
+    let source = read_source(filename);
+    let statements = parse_str(&source, filename);
+
+    let lints = lint::lint(&statements);
+
+    for lint in &lints {
+        let (line, column) = lint.place.location(&source);
+        println!("{}:{}:{}: {}", filename, line, column, lint.kind);
+    }
+
+    lints.len()
+}
+
+/// Run every test in the test files found in `paths`, printing how each one went along with a
+/// summary. Returns how many tests failed.
+pub fn test_programs(paths: &[&str]) -> usize {
+    let mut files = Vec::new();
+
+    for path in paths {
+        if let Err(e) = testing::find_test_files(Path::new(path), &mut files) {
+            eprintln!("Failed to look for tests in {}: {}", path, e);
+            std::process::exit(1);
+        }
+    }
+
+    let mut passed = 0;
+    let mut failures = Vec::new();
+
+    for file in &files {
+        let filename = file.display().to_string();
+
+        let source = match fs::read_to_string(file) {
+            Ok(source) => source,
+            Err(e) => {
+                failures.push(format!("{}: Failed to read file: {}", filename, e));
+                continue;
+            }
+        };
+
+        let statements = match grammar::file(&source) {
+            Ok(statements) => statements,
+            Err(e) => {
+                failures.push(format!(
+                    "{}:{}:{}: SYNTAX ERROR: expected {}",
+                    filename, e.location.line, e.location.column, e.expected
+                ));
+                continue;
+            }
+        };
+
+        let tests = testing::tests(&statements);
+        println!("running {} tests from {}", tests.len(), filename);
+
+        for test in &tests {
+            match testing::run_test(&filename, &statements, test) {
+                Ok(()) => {
+                    println!("test {} ... ok", test.name);
+                    passed += 1;
+                }
+                Err(e) => {
+                    println!("test {} ... FAILED", test.name);
+
+                    // Errors inside of builtins can't be pointed at in this file, so blame the
+                    // test itself for those
+                    let place = if testing::contains(&statements, e.place()) {
+                        e.place()
+                    } else {
+                        test.place
+                    };
+
+                    let (line, column) = place.location(&source);
+                    let first_line = source[place.start()..place.end()]
+                        .lines()
+                        .next()
+                        .unwrap_or_default();
+
+                    failures.push(format!(
+                        "{}:{}:{}: {}\n    {}\n{}",
+                        filename,
+                        line,
+                        column,
+                        test.name,
+                        first_line.trim(),
+                        e.kind()
+                    ));
+                }
+            }
+        }
+
+        println!();
+    }
+
+    if !failures.is_empty() {
+        println!("failures:\n");
+
+        for failure in &failures {
+            println!("{}\n", failure);
+        }
+    }
+
+    println!(
+        "test result: {}. {} passed; {} failed",
+        if failures.is_empty() { "ok" } else { "FAILED" },
+        passed,
+        failures.len()
+    );
+
+    failures.len()
 }
@@ -0,0 +1,326 @@
+use crate::{
+    builtins,
+    expression::Expression,
+    grammar,
+    statement::{Statement, StatementKind},
+    testing::{self, Test},
+    BUILTINS,
+};
+use std::{collections::HashMap, mem, ptr};
+
+#[cfg(test)]
+mod test;
+
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub enum LintKind {
+    #[error("You squanched {0} already Morty, it's not coming back!")]
+    UseAfterDelete(String),
+
+    #[error("There's no {0} in this universe, Morty!")]
+    UndefinedName(String),
+
+    #[error("{name} takes {expected} paramaters, but you're trying to give it {actual}.")]
+    ArityMismatch {
+        name: String,
+        expected: usize,
+        actual: usize,
+    },
+
+    #[error("Nothing after a return ever runs Morty, it's like it doesn't even exist.")]
+    UnreachableCode,
+
+    #[error("{0} can run and die like an animal without ever returning a value.")]
+    MissingReturn(String),
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Lint {
+    pub place: Statement,
+    pub kind: LintKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Binding {
+    Value,
+    Function(usize),
+    Deleted,
+}
+
+type Scope = HashMap<String, Binding>;
+
+#[derive(Default)]
+struct Linter<'a> {
+    lints: Vec<Lint>,
+    tests: Vec<Test<'a>>,
+}
+
+/// Check a parsed program for mistakes that would otherwise only show up at runtime.
+///
+/// Scoping follows the interpreter: the top level can see `argv`, `program` and the builtins,
+/// while a function body can only see its own parameters, the native builtins and the names it
+/// defines itself. Test functions run after the rest of the file instead of being called, so they
+/// see everything it defines and don't have to return anything.
+pub fn lint(statements: &[Statement]) -> Vec<Lint> {
+    let mut scope = builtin_scope();
+    scope.insert("argv".into(), Binding::Value);
+    scope.insert("program".into(), Binding::Value);
+
+    let mut linter = Linter {
+        tests: testing::tests(statements),
+        ..Linter::default()
+    };
+    linter.block(statements, &mut scope);
+
+    for test in mem::take(&mut linter.tests) {
+        linter.block(test.body, &mut scope.clone());
+    }
+
+    linter.lints
+}
+
+fn builtin_scope() -> Scope {
+    let statements = grammar::file(BUILTINS).expect("builtins should always parse");
+
+    statements
+        .iter()
+        .filter_map(|statement| match statement.kind {
+            StatementKind::Function(ref name, ref params, _) => {
+                Some((name.clone(), Binding::Function(params.len())))
+            }
+            _ => None,
+        })
+        .chain(native_scope())
+        .collect()
+}
+
+/// The native builtins, which unlike the ones from `builtins.y` can be called inside functions.
+fn native_scope() -> Scope {
+    builtins::NATIVE
+        .iter()
+        .map(|builtin| (builtin.name.into(), Binding::Function(builtin.params.len())))
+        .collect()
+}
+
+/// Combine the scopes of two paths that may have been taken. A name is only considered deleted
+/// or undefined if that is true on both paths.
+fn merge(a: &Scope, b: &Scope) -> Scope {
+    let mut merged = a.clone();
+
+    for (name, binding) in b {
+        let combined = match (a.get(name), *binding) {
+            (None, binding) | (Some(Binding::Deleted), binding) => binding,
+            (Some(&x), y) if x == y => y,
+            (Some(&x), Binding::Deleted) => x,
+            _ => Binding::Value,
+        };
+
+        merged.insert(name.clone(), combined);
+    }
+
+    merged
+}
+
+impl<'a> Linter<'a> {
+    fn report(&mut self, kind: LintKind, place: &Statement) {
+        self.lints.push(Lint {
+            kind,
+            place: place.clone(),
+        });
+    }
+
+    /// Returns true if every path through the block ends in a `return`.
+    fn block(&mut self, statements: &[Statement], scope: &mut Scope) -> bool {
+        for (idx, statement) in statements.iter().enumerate() {
+            if self.statement(statement, scope) {
+                if let Some(unreachable) = statements.get(idx + 1) {
+                    self.report(LintKind::UnreachableCode, unreachable);
+                }
+
+                return true;
+            }
+        }
+
+        false
+    }
+
+    fn statement(&mut self, statement: &Statement, scope: &mut Scope) -> bool {
+        match statement.kind {
+            StatementKind::Assignment(ref name, ref exp) => {
+                self.expression(exp, statement, scope);
+                scope.insert(name.clone(), Binding::Value);
+            }
+            StatementKind::Delete(ref name) => {
+                self.read(name, statement, scope);
+                scope.insert(name.clone(), Binding::Deleted);
+            }
+            StatementKind::Print(ref exp)
+            | StatementKind::PrintNoNl(ref exp)
+            | StatementKind::PrintErr(ref exp)
+            | StatementKind::PrintErrNoNl(ref exp)
+            | StatementKind::Assert(ref exp) => self.expression(exp, statement, scope),
+            StatementKind::ListNew(ref name)
+            | StatementKind::Input(ref name)
+            | StatementKind::InputRaw(ref name)
+            | StatementKind::InputAll(ref name) => {
+                scope.insert(name.clone(), Binding::Value);
+            }
+            StatementKind::ListAppend(ref name, ref path, ref exp)
+            | StatementKind::ListAssign(ref name, ref path, ref exp) => {
+                self.expression(exp, statement, scope);
+                for index_exp in path {
+                    self.expression(index_exp, statement, scope);
+                }
+                self.read(name, statement, scope);
+            }
+            StatementKind::ListDelete(ref name, ref path) => {
+                for index_exp in path {
+                    self.expression(index_exp, statement, scope);
+                }
+                self.read(name, statement, scope);
+            }
+            StatementKind::If(ref condition, ref if_body, ref else_body) => {
+                self.expression(condition, statement, scope);
+
+                let mut if_scope = scope.clone();
+                let if_returns = self.block(if_body, &mut if_scope);
+
+                let mut else_scope = scope.clone();
+                let else_returns = match *else_body {
+                    Some(ref body) => self.block(body, &mut else_scope),
+                    None => false,
+                };
+
+                *scope = match (if_returns, else_returns) {
+                    (true, _) => else_scope,
+                    (false, true) => if_scope,
+                    (false, false) => merge(&if_scope, &else_scope),
+                };
+
+                return if_returns && else_returns;
+            }
+            StatementKind::While(ref condition, ref body) => {
+                self.expression(condition, statement, scope);
+
+                let mut body_scope = scope.clone();
+                self.block(body, &mut body_scope);
+
+                *scope = merge(scope, &body_scope);
+            }
+            StatementKind::Catch(ref try_block, ref catch) => {
+                let mut try_scope = scope.clone();
+                let try_returns = self.block(try_block, &mut try_scope);
+
+                // The catch block can start from any point in the try block
+                let mut catch_scope = merge(scope, &try_scope);
+                let catch_returns = self.block(catch, &mut catch_scope);
+
+                *scope = if try_returns {
+                    catch_scope
+                } else {
+                    merge(&try_scope, &catch_scope)
+                };
+
+                return try_returns && catch_returns;
+            }
+            StatementKind::Function(ref name, ref params, _) if self.is_test(statement) => {
+                scope.insert(name.clone(), Binding::Function(params.len()));
+            }
+            StatementKind::Function(ref name, ref params, ref body) => {
+                let mut function_scope = native_scope();
+                for param in params {
+                    function_scope.insert(param.clone(), Binding::Value);
+                }
+
+                if !self.block(body, &mut function_scope) {
+                    self.report(LintKind::MissingReturn(name.clone()), statement);
+                }
+
+                scope.insert(name.clone(), Binding::Function(params.len()));
+            }
+            StatementKind::Return(ref exp) => {
+                self.expression(exp, statement, scope);
+                return true;
+            }
+            StatementKind::FunctionCall(ref name, ref args) => {
+                self.call(name, args, statement, scope)
+            }
+            StatementKind::DylibLoad(_, ref functions) => {
+                for function in functions {
+                    if let StatementKind::FunctionCall(ref name, _) = function.kind {
+                        scope.insert(name.clone(), Binding::Value);
+                    }
+                }
+            }
+        }
+
+        false
+    }
+
+    fn expression(&mut self, exp: &Expression, place: &Statement, scope: &Scope) {
+        match *exp {
+            Expression::Variable(ref name) | Expression::ListLength(ref name) => {
+                self.read(name, place, scope)
+            }
+            Expression::ListIndex(ref list, ref index_exp) => {
+                self.expression(index_exp, place, scope);
+                self.expression(list, place, scope);
+            }
+            Expression::Slice(ref list, ref slice) => {
+                for exp in [&slice.start, &slice.end, &slice.step]
+                    .iter()
+                    .copied()
+                    .flatten()
+                {
+                    self.expression(exp, place, scope);
+                }
+                self.expression(list, place, scope);
+            }
+            Expression::OpExp(ref left, _, ref right) => {
+                self.expression(left, place, scope);
+                self.expression(right, place, scope);
+            }
+            Expression::Not(ref exp) | Expression::Eval(ref exp) => {
+                self.expression(exp, place, scope)
+            }
+            Expression::List(ref items) => {
+                for item in items {
+                    self.expression(item, place, scope);
+                }
+            }
+            Expression::FunctionCall(ref name, ref args) => self.call(name, args, place, scope),
+            Expression::Value(_) => {}
+        }
+    }
+
+    fn call(&mut self, name: &str, args: &[Expression], place: &Statement, scope: &Scope) {
+        for arg in args {
+            self.expression(arg, place, scope);
+        }
+
+        match scope.get(name) {
+            Some(&Binding::Function(expected)) if expected != args.len() => self.report(
+                LintKind::ArityMismatch {
+                    name: name.to_string(),
+                    expected,
+                    actual: args.len(),
+                },
+                place,
+            ),
+            _ => self.read(name, place, scope),
+        }
+    }
+
+    fn is_test(&self, statement: &Statement) -> bool {
+        self.tests.iter().any(|test| ptr::eq(test.place, statement))
+    }
+
+    fn read(&mut self, name: &str, place: &Statement, scope: &Scope) {
+        match scope.get(name) {
+            Some(Binding::Deleted) => {
+                self.report(LintKind::UseAfterDelete(name.to_string()), place)
+            }
+            None => self.report(LintKind::UndefinedName(name.to_string()), place),
+            Some(_) => {}
+        }
+    }
+}
@@ -0,0 +1,189 @@
+use crate::{
+    grammar,
+    lint::{lint, LintKind as Kind},
+};
+
+fn lint_kinds(source: &str) -> Vec<Kind> {
+    let statements = grammar::file(source).unwrap();
+    lint(&statements).into_iter().map(|l| l.kind).collect()
+}
+
+#[test]
+fn test_clean_program_has_no_lints() {
+    let kinds = lint_kinds(
+        r#"
+    isPrime(x) :<
+        i squanch 2
+        while (i less x) :<
+            if ((x % i) == 0) :<
+                return morty
+            >:
+            i squanch (i + 1)
+        >:
+        return rick
+    >:
+
+    show me what you got isPrime(argv[0])
+    show me what you got ascii(65)
+    "#,
+    );
+
+    assert_eq!(kinds, vec![]);
+}
+
+#[test]
+fn test_use_after_delete() {
+    let kinds = lint_kinds(
+        r#"
+    x squanch 10
+    squanch x
+    show me what you got x
+    "#,
+    );
+
+    assert_eq!(kinds, vec![Kind::UseAfterDelete("x".into())]);
+}
+
+#[test]
+fn test_delete_in_one_branch_is_not_reported() {
+    let kinds = lint_kinds(
+        r#"
+    x squanch 10
+    if rick :<
+        squanch x
+    >:
+    show me what you got x
+    "#,
+    );
+
+    assert_eq!(kinds, vec![]);
+}
+
+#[test]
+fn test_undefined_name() {
+    let kinds = lint_kinds(
+        r#"
+    foo(x) :<
+        return (x + y)
+    >:
+    "#,
+    );
+
+    assert_eq!(kinds, vec![Kind::UndefinedName("y".into())]);
+}
+
+#[test]
+fn test_arity_mismatch() {
+    let kinds = lint_kinds(
+        r#"
+    foo(x, y) :<
+        return (x + y)
+    >:
+    z squanch foo(1)
+    ascii(1, 2)
+    "#,
+    );
+
+    assert_eq!(
+        kinds,
+        vec![
+            Kind::ArityMismatch {
+                name: "foo".into(),
+                expected: 2,
+                actual: 1,
+            },
+            Kind::ArityMismatch {
+                name: "ascii".into(),
+                expected: 1,
+                actual: 2,
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_unreachable_code() {
+    let kinds = lint_kinds(
+        r#"
+    foo(x) :<
+        if x :<
+            return 1
+        >: else :<
+            return 2
+        >:
+        show me what you got x
+    >:
+    "#,
+    );
+
+    assert_eq!(kinds, vec![Kind::UnreachableCode]);
+}
+
+#[test]
+fn test_missing_return() {
+    let kinds = lint_kinds(
+        r#"
+    foo(x) :<
+        if x :<
+            return 1
+        >:
+    >:
+    "#,
+    );
+
+    assert_eq!(kinds, vec![Kind::MissingReturn("foo".into())]);
+}
+
+#[test]
+fn test_native_builtins() {
+    let kinds = lint_kinds(
+        r#"
+    contents squanch readFile("portal.txt")
+    fileExists("portal.txt", 1)
+
+    read(path) :<
+        return readFile(path)
+    >:
+    "#,
+    );
+
+    assert_eq!(
+        kinds,
+        vec![Kind::ArityMismatch {
+            name: "fileExists".into(),
+            expected: 1,
+            actual: 2,
+        }]
+    );
+}
+
+#[test]
+fn test_test_functions_see_the_whole_file() {
+    let kinds = lint_kinds(
+        r#"
+    test_double() :<
+        wubba lubba dub dub (double(x) == 4)
+        wubba lubba dub dub (y == 1)
+    >:
+
+    double(n) :<
+        return (n * 2)
+    >:
+
+    x squanch 2
+
+    helper() :<
+        wubba lubba dub dub (x == 2)
+    >:
+    "#,
+    );
+
+    assert_eq!(
+        kinds,
+        vec![
+            Kind::UndefinedName("x".into()),
+            Kind::MissingReturn("helper".into()),
+            Kind::UndefinedName("y".into()),
+        ]
+    );
+}
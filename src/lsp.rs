@@ -0,0 +1,441 @@
+use crate::{
+    builtins::{self, Builtin},
+    grammar, lint, rpc,
+    statement::{walk, Statement, StatementKind},
+    BUILTINS,
+};
+use serde_json::{json, Value as Json};
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, Write},
+};
+
+const KEYWORDS: [&str; 26] = [
+    "squanch",
+    "on a cob",
+    "assimilate",
+    "show me what you got",
+    "show me what you got!",
+    "existence is pain",
+    "existence is pain!",
+    "portal gun",
+    "wubba lubba dub dub",
+    "if",
+    "else",
+    "while",
+    "return",
+    "normal plan",
+    "plan for failure",
+    "microverse",
+    "rick",
+    "morty",
+    "more",
+    "less",
+    "moresquanch",
+    "lesssquanch",
+    "and",
+    "or",
+    "<schwift",
+    "schwift>",
+];
+
+const METHOD_NOT_FOUND: i64 = -32601;
+
+const SEVERITY_ERROR: u8 = 1;
+const SEVERITY_WARNING: u8 = 2;
+
+const SYMBOL_FUNCTION: u8 = 12;
+const SYMBOL_VARIABLE: u8 = 13;
+
+const COMPLETION_FUNCTION: u8 = 3;
+const COMPLETION_KEYWORD: u8 = 14;
+
+struct Document {
+    text: String,
+    statements: Option<Vec<Statement>>,
+}
+
+struct Server<W> {
+    output: W,
+    documents: HashMap<String, Document>,
+    builtins: Vec<Statement>,
+}
+
+/// Run a language server, reading requests from `input` and writing responses to `output` until
+/// the client asks it to exit.
+pub fn serve<R, W>(mut input: R, output: W) -> io::Result<()>
+where
+    R: BufRead,
+    W: Write,
+{
+    let mut server = Server {
+        output,
+        documents: HashMap::new(),
+        builtins: grammar::file(BUILTINS).expect("builtins should always parse"),
+    };
+
+    while let Some(message) = rpc::read_message(&mut input)? {
+        if message["method"] == "exit" {
+            break;
+        }
+
+        server.handle(&message)?;
+    }
+
+    Ok(())
+}
+
+impl<W> Server<W>
+where
+    W: Write,
+{
+    fn handle(&mut self, message: &Json) -> io::Result<()> {
+        let params = &message["params"];
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+
+        let result = match message["method"].as_str().unwrap_or_default() {
+            "initialize" => capabilities(),
+            "shutdown" => Json::Null,
+            "textDocument/didOpen" => {
+                let text = params["textDocument"]["text"].as_str().unwrap_or_default();
+                return self.update(uri, text.to_string());
+            }
+            "textDocument/didChange" => {
+                let changes = params["contentChanges"].as_array();
+                return match changes.and_then(|c| c.last()) {
+                    Some(change) => {
+                        let text = change["text"].as_str().unwrap_or_default();
+                        self.update(uri, text.to_string())
+                    }
+                    None => Ok(()),
+                };
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(uri);
+                return self.publish_diagnostics(uri, Vec::new());
+            }
+            "textDocument/hover" => self.hover(uri, &params["position"]),
+            "textDocument/definition" => self.definition(uri, &params["position"]),
+            "textDocument/completion" => self.completion(uri),
+            "textDocument/documentSymbol" => self.document_symbols(uri),
+            method => {
+                return match message.get("id") {
+                    Some(id) => self.send(&json!({
+                        "jsonrpc": "2.0",
+                        "id": id,
+                        "error": {
+                            "code": METHOD_NOT_FOUND,
+                            "message": format!("I don't know how to {}, Morty", method),
+                        },
+                    })),
+                    None => Ok(()),
+                };
+            }
+        };
+
+        self.send(&json!({
+            "jsonrpc": "2.0",
+            "id": message["id"],
+            "result": result,
+        }))
+    }
+
+    fn send(&mut self, message: &Json) -> io::Result<()> {
+        rpc::write_message(&mut self.output, message)
+    }
+
+    fn update(&mut self, uri: &str, text: String) -> io::Result<()> {
+        let mut diagnostics = Vec::new();
+
+        let statements = match grammar::file(&text) {
+            Ok(statements) => {
+                for lint in lint::lint(&statements) {
+                    diagnostics.push(json!({
+                        "range": range(&text, lint.place.start(), lint.place.end()),
+                        "severity": SEVERITY_WARNING,
+                        "source": "schwift",
+                        "message": lint.kind.to_string(),
+                    }));
+                }
+
+                Some(statements)
+            }
+            Err(e) => {
+                let offset = e.location.offset;
+                diagnostics.push(json!({
+                    "range": range(&text, offset, offset),
+                    "severity": SEVERITY_ERROR,
+                    "source": "schwift",
+                    "message": format!("SYNTAX ERROR: expected {}", e.expected),
+                }));
+
+                None
+            }
+        };
+
+        self.documents
+            .insert(uri.to_string(), Document { text, statements });
+
+        self.publish_diagnostics(uri, diagnostics)
+    }
+
+    fn publish_diagnostics(&mut self, uri: &str, diagnostics: Vec<Json>) -> io::Result<()> {
+        self.send(&json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/publishDiagnostics",
+            "params": {
+                "uri": uri,
+                "diagnostics": diagnostics,
+            },
+        }))
+    }
+
+    fn hover(&self, uri: &str, position: &Json) -> Json {
+        let (doc, statements) = match self.parsed(uri) {
+            Some(x) => x,
+            None => return Json::Null,
+        };
+
+        let (start, end) = match offset(&doc.text, position).and_then(|o| word_at(&doc.text, o)) {
+            Some(x) => x,
+            None => return Json::Null,
+        };
+        let word = &doc.text[start..end];
+
+        let definition = find_function(statements, word)
+            .or_else(|| find_function(&self.builtins, word))
+            .map(|statement| signature(statement).unwrap_or_default())
+            .or_else(|| {
+                builtins::NATIVE
+                    .iter()
+                    .find(|builtin| builtin.name == word)
+                    .map(Builtin::signature)
+            });
+
+        match definition {
+            Some(signature) => json!({
+                "contents": {
+                    "kind": "markdown",
+                    "value": format!("```schwift\n{}\n```", signature),
+                },
+                "range": range(&doc.text, start, end),
+            }),
+            None => Json::Null,
+        }
+    }
+
+    fn definition(&self, uri: &str, position: &Json) -> Json {
+        let (doc, statements) = match self.parsed(uri) {
+            Some(x) => x,
+            None => return Json::Null,
+        };
+
+        let word = match offset(&doc.text, position).and_then(|o| word_at(&doc.text, o)) {
+            Some((start, end)) => &doc.text[start..end],
+            None => return Json::Null,
+        };
+
+        match find_function(statements, word) {
+            Some(statement) => json!({
+                "uri": uri,
+                "range": range(&doc.text, statement.start(), statement.start() + word.len()),
+            }),
+            None => Json::Null,
+        }
+    }
+
+    fn completion(&self, uri: &str) -> Json {
+        let mut items: Vec<Json> = KEYWORDS
+            .iter()
+            .map(|keyword| {
+                json!({
+                    "label": keyword,
+                    "kind": COMPLETION_KEYWORD,
+                })
+            })
+            .collect();
+
+        for builtin in &builtins::NATIVE {
+            items.push(json!({
+                "label": builtin.name,
+                "kind": COMPLETION_FUNCTION,
+                "detail": builtin.signature(),
+            }));
+        }
+
+        let mut functions = Vec::new();
+        walk(&self.builtins, &mut |s| functions.push(s));
+        if let Some((_, statements)) = self.parsed(uri) {
+            walk(statements, &mut |s| functions.push(s));
+        }
+
+        for statement in functions {
+            if let StatementKind::Function(ref name, _, _) = statement.kind {
+                items.push(json!({
+                    "label": name,
+                    "kind": COMPLETION_FUNCTION,
+                    "detail": signature(statement),
+                }));
+            }
+        }
+
+        Json::Array(items)
+    }
+
+    fn document_symbols(&self, uri: &str) -> Json {
+        match self.parsed(uri) {
+            Some((doc, statements)) => Json::Array(symbols(&doc.text, statements)),
+            None => Json::Null,
+        }
+    }
+
+    fn parsed(&self, uri: &str) -> Option<(&Document, &[Statement])> {
+        let doc = self.documents.get(uri)?;
+        let statements = doc.statements.as_ref()?;
+
+        Some((doc, statements))
+    }
+}
+
+fn capabilities() -> Json {
+    json!({
+        "capabilities": {
+            "textDocumentSync": 1,
+            "hoverProvider": true,
+            "definitionProvider": true,
+            "completionProvider": {},
+            "documentSymbolProvider": true,
+        },
+        "serverInfo": {
+            "name": "schwift",
+        },
+    })
+}
+
+fn find_function<'a>(statements: &'a [Statement], name: &str) -> Option<&'a Statement> {
+    let mut found = None;
+
+    walk(statements, &mut |statement| {
+        if let StatementKind::Function(ref n, _, _) = statement.kind {
+            if n == name && found.is_none() {
+                found = Some(statement);
+            }
+        }
+    });
+
+    found
+}
+
+fn signature(statement: &Statement) -> Option<String> {
+    match statement.kind {
+        StatementKind::Function(ref name, ref params, _) => {
+            Some(format!("{}({})", name, params.join(", ")))
+        }
+        _ => None,
+    }
+}
+
+fn symbols(text: &str, statements: &[Statement]) -> Vec<Json> {
+    let mut symbols = Vec::new();
+    let mut seen = Vec::new();
+
+    for statement in statements {
+        let full_range = range(text, statement.start(), statement.end());
+
+        match statement.kind {
+            StatementKind::Function(ref name, _, ref body) => {
+                symbols.push(json!({
+                    "name": name,
+                    "detail": signature(statement),
+                    "kind": SYMBOL_FUNCTION,
+                    "range": full_range,
+                    "selectionRange": range(text, statement.start(), statement.start() + name.len()),
+                    "children": self::symbols(text, body),
+                }));
+            }
+            StatementKind::Assignment(ref name, _)
+            | StatementKind::ListNew(ref name)
+            | StatementKind::Input(ref name)
+            | StatementKind::InputRaw(ref name)
+            | StatementKind::InputAll(ref name) => {
+                if seen.contains(name) {
+                    continue;
+                }
+                seen.push(name.clone());
+
+                let selection = if text[statement.start()..].starts_with(name.as_str()) {
+                    range(text, statement.start(), statement.start() + name.len())
+                } else {
+                    full_range.clone()
+                };
+
+                symbols.push(json!({
+                    "name": name,
+                    "kind": SYMBOL_VARIABLE,
+                    "range": full_range,
+                    "selectionRange": selection,
+                }));
+            }
+            _ => {}
+        }
+    }
+
+    symbols
+}
+
+/// The byte range of the identifier that contains `offset`, if there is one.
+fn word_at(text: &str, offset: usize) -> Option<(usize, usize)> {
+    let is_ident = |c: char| c.is_ascii_alphanumeric() || c == '_';
+
+    let start = text[..offset]
+        .rfind(|c| !is_ident(c))
+        .map_or(0, |idx| idx + 1);
+    let end = text[offset..]
+        .find(|c| !is_ident(c))
+        .map_or(text.len(), |idx| offset + idx);
+
+    if start < end {
+        Some((start, end))
+    } else {
+        None
+    }
+}
+
+fn range(text: &str, start: usize, end: usize) -> Json {
+    json!({
+        "start": position(text, start),
+        "end": position(text, end),
+    })
+}
+
+/// Convert a byte offset to an LSP position, which counts characters in UTF-16 code units.
+fn position(text: &str, offset: usize) -> Json {
+    let before = &text[..offset];
+    let line = before.matches('\n').count();
+    let line_start = before.rfind('\n').map_or(0, |idx| idx + 1);
+
+    json!({
+        "line": line,
+        "character": text[line_start..offset].encode_utf16().count(),
+    })
+}
+
+fn offset(text: &str, position: &Json) -> Option<usize> {
+    let line = position["line"].as_u64()? as usize;
+    let character = position["character"].as_u64()? as usize;
+
+    let line_start = match line {
+        0 => 0,
+        _ => text.match_indices('\n').nth(line - 1)?.0 + 1,
+    };
+
+    let mut units = 0;
+    for (idx, c) in text[line_start..].char_indices() {
+        if units >= character || c == '\n' {
+            return Some(line_start + idx);
+        }
+        units += c.len_utf16();
+    }
+
+    Some(text.len())
+}
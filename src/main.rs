@@ -1,14 +1,74 @@
-use clap::{App, AppSettings, Arg};
+use clap::{App, AppSettings, Arg, SubCommand};
 
 fn main() {
     let matches = App::new("The Schwift interpreter")
         .version("0.1")
         .setting(AppSettings::TrailingVarArg)
+        .setting(AppSettings::SubcommandsNegateReqs)
+        // Source files like tests/foo.y aren't typos of the test subcommand, and arguments
+        // passed on to the program aren't subcommands either
+        .setting(AppSettings::ArgsNegateSubcommands)
+        .setting(AppSettings::AllowExternalSubcommands)
         .author("Nate Mara <natemara@gmail.com>")
         .about(
             "The canonical interpreter for the schwift programming language. Use at your own \
              risk.",
         )
+        .arg(
+            Arg::with_name("debug")
+                .long("debug")
+                .help("Run the program in the step debugger"),
+        )
+        .arg(
+            Arg::with_name("break")
+                .long("break")
+                .value_name("LINE")
+                .help("Pause the debugger when it reaches LINE")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .requires("debug"),
+        )
+        .arg(
+            Arg::with_name("trace")
+                .long("trace")
+                .help("Print every statement to stderr as it runs")
+                .conflicts_with("debug"),
+        )
+        .arg(
+            Arg::with_name("profile")
+                .long("profile")
+                .help("Print how often each function and line ran once the program finishes")
+                .conflicts_with("debug"),
+        )
+        .arg(
+            Arg::with_name("profile-folded")
+                .long("profile-folded")
+                .value_name("FILE")
+                .help("Write the profile to FILE as folded stacks for flamegraph tools")
+                .takes_value(true)
+                .number_of_values(1)
+                .conflicts_with("debug"),
+        )
+        .arg(
+            Arg::with_name("coverage")
+                .long("coverage")
+                .value_name("FILE")
+                .help("Write an lcov report of which lines and branches ran to FILE")
+                .takes_value(true)
+                .number_of_values(1)
+                .conflicts_with("debug"),
+        )
+        .arg(
+            Arg::with_name("sandbox")
+                .long("sandbox")
+                .help("Don't let the program run other programs, load microverses or write files"),
+        )
+        .arg(
+            Arg::with_name("ieee-floats")
+                .long("ieee-floats")
+                .help("Let floats be divided by zero, giving infinity or NaN instead of an error"),
+        )
         .arg(
             Arg::with_name("SOURCE")
                 .value_name("FILE")
@@ -22,12 +82,127 @@ fn main() {
                 .multiple(true)
                 .takes_value(true),
         )
+        .subcommand(
+            SubCommand::with_name("lint")
+                .about("Check schwift source files for common mistakes without running them")
+                .arg(
+                    Arg::with_name("FILES")
+                        .value_name("FILE")
+                        .help("The schwift source files that you want to check")
+                        .multiple(true)
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("test")
+                .about("Run the test functions in schwift test files")
+                .arg(
+                    Arg::with_name("PATHS")
+                        .value_name("PATH")
+                        .help(
+                            "Test files, or directories to search for files ending in _test.y \
+                             (defaults to the current directory)",
+                        )
+                        .multiple(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("lsp")
+                .about("Run a language server for schwift that talks over stdin and stdout"),
+        )
+        .subcommand(SubCommand::with_name("dap").about(
+            "Run a debug adapter for schwift that speaks the Debug Adapter Protocol over \
+                 stdin and stdout",
+        ))
         .get_matches();
 
+    if let Some(lint_matches) = matches.subcommand_matches("lint") {
+        let mut lints = 0;
+
+        for filename in lint_matches.values_of("FILES").unwrap() {
+            lints += schwift::lint_program(filename);
+        }
+
+        if lints > 0 {
+            std::process::exit(1);
+        }
+
+        return;
+    }
+
+    if let Some(test_matches) = matches.subcommand_matches("test") {
+        let paths = match test_matches.values_of("PATHS") {
+            Some(paths) => paths.collect(),
+            None => vec!["."],
+        };
+
+        if schwift::test_programs(&paths) > 0 {
+            std::process::exit(1);
+        }
+
+        return;
+    }
+
+    if matches.subcommand_matches("lsp").is_some() {
+        let stdin = std::io::stdin();
+        let stdout = std::io::stdout();
+
+        if let Err(e) = schwift::lsp::serve(stdin.lock(), stdout.lock()) {
+            eprintln!("The language server broke down Morty: {}", e);
+            std::process::exit(1);
+        }
+
+        return;
+    }
+
+    if matches.subcommand_matches("dap").is_some() {
+        if let Err(e) = schwift::dap::serve(std::io::stdin(), std::io::stdout()) {
+            eprintln!("The debug adapter broke down Morty: {}", e);
+            std::process::exit(1);
+        }
+
+        return;
+    }
+
     let args = match matches.values_of("args") {
         Some(x) => x.collect(),
         None => Vec::new(),
     };
 
-    schwift::run_program(matches.value_of("SOURCE").unwrap(), &args);
+    let filename = matches.value_of("SOURCE").unwrap();
+    let run_options = schwift::RunOptions {
+        sandbox: matches.is_present("sandbox"),
+        ieee_floats: matches.is_present("ieee-floats"),
+    };
+
+    if matches.is_present("debug") {
+        let mut breakpoints = Vec::new();
+
+        for line in matches.values_of("break").into_iter().flatten() {
+            match line.parse() {
+                Ok(line) => breakpoints.push(line),
+                Err(_) => {
+                    eprintln!("{} isn't a line number, Morty", line);
+                    std::process::exit(1);
+                }
+            }
+        }
+
+        schwift::debug_program(filename, &args, &breakpoints, &run_options);
+    } else if matches.is_present("trace")
+        || matches.is_present("profile")
+        || matches.is_present("profile-folded")
+        || matches.is_present("coverage")
+    {
+        let options = schwift::TraceOptions {
+            trace: matches.is_present("trace"),
+            profile: matches.is_present("profile"),
+            folded: matches.value_of("profile-folded"),
+            coverage: matches.value_of("coverage"),
+        };
+
+        schwift::trace_program(filename, &args, &options, &run_options);
+    } else {
+        schwift::run_program(filename, &args, &run_options);
+    }
 }
@@ -0,0 +1,152 @@
+use crate::{hook::Hook, state::State, statement::Statement, util::LineIndex};
+use std::{
+    cmp::Reverse,
+    collections::BTreeMap,
+    io::{self, Write},
+    time::{Duration, Instant},
+};
+
+const MAIN: &str = "main";
+
+#[derive(Default)]
+struct FunctionStats {
+    calls: u64,
+    inclusive: Duration,
+    exclusive: Duration,
+}
+
+struct Frame {
+    name: String,
+    /// The whole call stack, in the `main;foo;bar` format that flamegraph tools want.
+    stack: String,
+    start: Instant,
+    children: Duration,
+}
+
+/// Counts how often each line and function runs, and how long the functions take.
+///
+/// Time spent in builtins and microverse functions is counted as part of whatever called them.
+pub struct Profiler {
+    filename: String,
+    lines: LineIndex,
+    line_counts: BTreeMap<usize, u64>,
+    functions: BTreeMap<String, FunctionStats>,
+    folded: BTreeMap<String, Duration>,
+    stack: Vec<Frame>,
+}
+
+impl Profiler {
+    pub fn new(filename: &str, source: &str) -> Self {
+        Self {
+            filename: filename.into(),
+            lines: LineIndex::new(source),
+            line_counts: BTreeMap::new(),
+            functions: BTreeMap::new(),
+            folded: BTreeMap::new(),
+            stack: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, name: &str) {
+        let stack = match self.stack.last() {
+            Some(parent) => format!("{};{}", parent.stack, name),
+            None => name.to_string(),
+        };
+
+        self.stack.push(Frame {
+            name: name.into(),
+            stack,
+            start: Instant::now(),
+            children: Duration::default(),
+        });
+    }
+
+    fn pop(&mut self) {
+        let frame = match self.stack.pop() {
+            Some(frame) => frame,
+            None => return,
+        };
+
+        let inclusive = frame.start.elapsed();
+        let exclusive = inclusive.saturating_sub(frame.children);
+
+        let stats = self.functions.entry(frame.name).or_default();
+        stats.calls += 1;
+        stats.inclusive += inclusive;
+        stats.exclusive += exclusive;
+
+        *self.folded.entry(frame.stack).or_default() += exclusive;
+
+        if let Some(parent) = self.stack.last_mut() {
+            parent.children += inclusive;
+        }
+    }
+
+    /// Stop the clock on everything that is still running. Call this once the program is done.
+    pub fn finish(&mut self) {
+        while !self.stack.is_empty() {
+            self.pop();
+        }
+    }
+
+    pub fn report<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        writeln!(
+            out,
+            "{:<24} {:>8} {:>16} {:>16}",
+            "function", "calls", "inclusive (ms)", "exclusive (ms)"
+        )?;
+
+        let mut functions: Vec<_> = self.functions.iter().collect();
+        functions.sort_by_key(|(_, stats)| Reverse(stats.inclusive));
+
+        for (name, stats) in functions {
+            writeln!(
+                out,
+                "{:<24} {:>8} {:>16.3} {:>16.3}",
+                name,
+                stats.calls,
+                stats.inclusive.as_secs_f64() * 1000.0,
+                stats.exclusive.as_secs_f64() * 1000.0,
+            )?;
+        }
+
+        writeln!(out)?;
+        writeln!(out, "{:<24} {:>8}", "line", "count")?;
+
+        for (line, count) in &self.line_counts {
+            let place = format!("{}:{}", self.filename, line);
+            writeln!(out, "{:<24} {:>8}", place, count)?;
+        }
+
+        Ok(())
+    }
+
+    /// Write the exclusive time of every call stack in microseconds, one per line, in the folded
+    /// format that tools like `flamegraph.pl` and `inferno` read.
+    pub fn write_folded<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        for (stack, time) in &self.folded {
+            writeln!(out, "{} {}", stack, time.as_micros())?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Hook for Profiler {
+    fn statement(&mut self, _state: &mut State, statement: &Statement) {
+        if self.stack.is_empty() {
+            self.push(MAIN);
+        }
+
+        let line = self.lines.line(statement.start());
+        *self.line_counts.entry(line).or_default() += 1;
+    }
+
+    fn enter_function(&mut self, name: &str) {
+        self.push(name);
+    }
+
+    fn exit_function(&mut self, _name: &str) {
+        self.pop();
+    }
+}
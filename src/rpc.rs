@@ -0,0 +1,46 @@
+//! Reading and writing the `Content-Length` framed JSON messages used by both the language server
+//! and debug adapter protocols.
+
+use serde_json::Value as Json;
+use std::io::{self, BufRead, Write};
+
+/// Read a single message, returning `None` once the input has been closed.
+pub fn read_message<R: BufRead>(input: &mut R) -> io::Result<Option<Json>> {
+    let mut length = None;
+
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+
+        let header = header.trim_end();
+        if header.is_empty() {
+            if length.is_some() {
+                break;
+            }
+
+            continue;
+        }
+
+        if let Some(value) = header.strip_prefix("Content-Length:") {
+            let parsed = value
+                .trim()
+                .parse::<usize>()
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            length = Some(parsed);
+        }
+    }
+
+    let mut body = vec![0; length.unwrap_or_default()];
+    input.read_exact(&mut body)?;
+
+    Ok(Some(serde_json::from_slice(&body)?))
+}
+
+pub fn write_message<W: Write>(output: &mut W, message: &Json) -> io::Result<()> {
+    let body = message.to_string();
+
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()
+}
@@ -1,12 +1,21 @@
 use crate::{
+    builtins::{self, Capability},
     error::{ErrorKind, ErrorKindExt, ErrorWithContext, SwResult},
-    expression::Expression,
+    expression::{Expression, Slice},
     grammar,
+    hook::Hook,
     statement::{Statement, StatementKind},
-    value::{self, Value},
+    value::{self, Func, Value},
     vec_map::VecMap,
 };
-use std::{borrow, io, mem};
+use std::{
+    borrow,
+    cell::RefCell,
+    collections::HashSet,
+    io::{self, BufRead, Write},
+    mem,
+    rc::Rc,
+};
 
 type Map<K, V> = VecMap<K, V>;
 
@@ -17,6 +26,20 @@ pub struct State {
     symbols: Map<String, Value>,
     last_return: Option<Value>,
     libraries: Vec<libloading::Library>,
+    hook: Option<Hooked>,
+    input: Rc<RefCell<dyn BufRead>>,
+    output: Rc<RefCell<dyn Write>>,
+    error_output: Rc<RefCell<dyn Write>>,
+    denied: Rc<HashSet<Capability>>,
+    ieee_floats: bool,
+}
+
+/// A hook along with the names of the functions that were defined while it was installed, which
+/// are the only ones it gets to see inside of.
+#[derive(Clone)]
+struct Hooked {
+    hook: Rc<RefCell<dyn Hook>>,
+    functions: Rc<RefCell<HashSet<String>>>,
 }
 
 // macro_rules! error {
@@ -46,60 +69,33 @@ pub struct State {
 impl State {
     pub fn list_index<'a>(
         &'a self,
-        list_name: &str,
-        exp: &Expression,
+        list_exp: &'a Expression,
+        index_exp: &Expression,
     ) -> SwResult<borrow::Cow<'a, Value>> {
-        let inner_expression_value = exp.evaluate(self)?.into_owned();
-        match self.symbols.get(list_name) {
-            Some(symbol) => match *symbol {
-                Value::List(ref l) => {
-                    if let Value::Int(i) = inner_expression_value {
-                        let index = i as usize;
-                        if index < l.len() {
-                            Ok(borrow::Cow::Borrowed(&l[index]))
-                        } else {
-                            Err(ErrorKind::IndexOutOfBounds {
-                                len: l.len(),
-                                index,
-                            }
-                            .into())
-                        }
-                    } else {
-                        Err(ErrorKind::UnexpectedType {
-                            expected: value::Type::Int,
-                            actual: inner_expression_value.get_type(),
-                        }
-                        .into())
-                    }
-                }
-                Value::Str(ref s) => {
-                    if let Value::Int(i) = inner_expression_value {
-                        let index = i as usize;
-                        let chars: Vec<char> = s.chars().collect();
-
-                        if index < chars.len() {
-                            Ok(borrow::Cow::Owned(Value::Str(chars[index].to_string())))
-                        } else {
-                            Err(ErrorKind::IndexOutOfBounds {
-                                len: chars.len(),
-                                index,
-                            }
-                            .into())
-                        }
-                    } else {
-                        Err(ErrorKind::UnexpectedType {
-                            expected: value::Type::Int,
-                            actual: inner_expression_value.get_type(),
-                        }
-                        .into())
-                    }
-                }
-                _ => Err(ErrorKind::IndexUnindexable(symbol.get_type()).into()),
-            },
-            None => Err(ErrorKind::UnknownVariable(list_name.to_string()).into()),
+        let list = list_exp.evaluate(self)?;
+        let index = index_exp.try_int(self)?;
+
+        match list {
+            borrow::Cow::Borrowed(list) => list.index(index),
+            borrow::Cow::Owned(list) => list
+                .index(index)
+                .map(|element| borrow::Cow::Owned(element.into_owned())),
         }
     }
 
+    pub fn slice(&self, list_exp: &Expression, slice: &Slice) -> SwResult<Value> {
+        let bound = |exp: &Option<Expression>| match *exp {
+            Some(ref exp) => exp.try_int(self).map(Some),
+            None => Ok(None),
+        };
+
+        let start = bound(&slice.start)?;
+        let end = bound(&slice.end)?;
+        let step = bound(&slice.step)?.unwrap_or(1);
+
+        list_exp.evaluate(self)?.slice(start, end, step)
+    }
+
     pub fn call_function(&self, name: &str, args: &[Expression]) -> SwResult<Value> {
         let mut call_args = Vec::new();
 
@@ -107,28 +103,57 @@ impl State {
             call_args.push(x.evaluate(self)?.into_owned());
         }
 
-        if let Value::NativeFunction(ref funk) = *self.get(name)? {
-            return funk.call(&mut call_args);
-        }
+        let function = match self.symbols.get(name) {
+            Some(function) => function,
+            // Functions run in a state of their own, so rather than copying the native builtins
+            // into every one of those they're looked up here
+            None => match builtins::native(name) {
+                Some(builtin) => return self.call_native(name, &builtin.into(), &mut call_args),
+                None => return Err(ErrorKind::UnknownVariable(name.to_string()).into()),
+            },
+        };
 
-        match self.get(name)? {
+        match function {
+            Value::NativeFunction(ref funk) => self.call_native(name, funk, &mut call_args),
             Value::Function(ref params, ref body) => {
                 if args.len() != params.len() {
                     return Err(ErrorKind::InvalidArguments(
                         name.to_string(),
-                        args.len(),
                         params.len(),
+                        args.len(),
                     )
                     .into());
                 }
 
-                let mut child_state = Self::default();
+                let mut child_state = Self {
+                    input: self.input.clone(),
+                    output: self.output.clone(),
+                    error_output: self.error_output.clone(),
+                    denied: self.denied.clone(),
+                    ieee_floats: self.ieee_floats,
+                    ..Self::default()
+                };
 
                 for (name, arg) in params.iter().zip(call_args) {
                     child_state.symbols.insert(name.to_string(), arg);
                 }
 
-                child_state.run(body)?;
+                child_state.hook = self
+                    .hook
+                    .clone()
+                    .filter(|hooked| hooked.functions.borrow().contains(name));
+
+                if let Some(ref hooked) = child_state.hook {
+                    hooked.hook.borrow_mut().enter_function(name);
+                }
+
+                let result = child_state.run(body);
+
+                if let Some(ref hooked) = child_state.hook {
+                    hooked.hook.borrow_mut().exit_function(name);
+                }
+
+                result?;
 
                 let last_ret = mem::replace(&mut child_state.last_return, None);
 
@@ -145,6 +170,16 @@ impl State {
         }
     }
 
+    fn call_native(&self, name: &str, funk: &Func, args: &mut Vec<Value>) -> SwResult<Value> {
+        if let Some(capability) = funk.capability() {
+            if !self.allows(capability) {
+                return Err(ErrorKind::Sandboxed(name.to_string()).into());
+            }
+        }
+
+        funk.call(args)
+    }
+
     pub fn get(&self, name: &str) -> SwResult<&Value> {
         match self.symbols.get(name) {
             Some(val) => Ok(val),
@@ -165,35 +200,92 @@ impl State {
         }
     }
 
-    fn print(&mut self, exp: &Expression) -> SwResult<()> {
+    fn print(&self, output: &RefCell<dyn Write>, exp: &Expression, newline: bool) -> SwResult<()> {
         let x = exp.evaluate(self)?;
-        x.println();
+        let mut output = output.borrow_mut();
+        x.write(&mut *output)?;
+
+        if newline {
+            writeln!(output)?;
+        } else {
+            output.flush()?;
+        }
+
         Ok(())
     }
 
-    fn print_no_nl(&mut self, exp: &Expression) -> SwResult<()> {
-        let x = exp.evaluate(self)?;
-        x.print();
+    fn assert(&mut self, exp: &Expression) -> SwResult<()> {
+        if exp.try_bool(self)? {
+            Ok(())
+        } else {
+            Err(ErrorKind::AssertionFailed.into())
+        }
+    }
+
+    /// Read a line into `name`, or `morty` if there's nothing left to read.
+    fn input(&mut self, name: String, trim: bool) -> SwResult<()> {
+        let mut input = String::new();
+
+        let value = if self.read_line(&mut input)? == 0 {
+            Value::Bool(false)
+        } else if trim {
+            Value::Str(input.trim().into())
+        } else {
+            Value::Str(input.into())
+        };
+        self.symbols.insert(name, value);
+
         Ok(())
     }
 
-    fn input(&mut self, name: String) -> SwResult<()> {
+    fn input_all(&mut self, name: String) -> SwResult<()> {
         let mut input = String::new();
 
-        io::stdin().read_line(&mut input)?;
+        self.output.borrow_mut().flush()?;
+        self.input.borrow_mut().read_to_string(&mut input)?;
 
-        input = input.trim().to_string();
-        self.symbols.insert(name, Value::Str(input));
+        self.symbols.insert(name, Value::Str(input.into()));
 
         Ok(())
     }
 
-    fn list_append(&mut self, list_name: &str, append_exp: &Expression) -> SwResult<()> {
+    pub(crate) fn read_line(&self, buf: &mut String) -> io::Result<usize> {
+        self.output.borrow_mut().flush()?;
+        self.input.borrow_mut().read_line(buf)
+    }
+
+    /// Read `portal gun` input from `input` instead of stdin. Functions called from here on
+    /// share it.
+    pub fn set_input(&mut self, input: Rc<RefCell<dyn BufRead>>) {
+        self.input = input;
+    }
+
+    /// Send everything that `show me what you got` prints to `output` instead of stdout. Keep a
+    /// clone of the `Rc` to get at what was written once the program is done.
+    pub fn set_output(&mut self, output: Rc<RefCell<dyn Write>>) {
+        self.output = output;
+    }
+
+    /// Send everything that `existence is pain` prints to `output` instead of stderr.
+    pub fn set_error_output(&mut self, output: Rc<RefCell<dyn Write>>) {
+        self.error_output = output;
+    }
+
+    fn list_append(
+        &mut self,
+        list_name: &str,
+        path: &[Expression],
+        append_exp: &Expression,
+    ) -> SwResult<()> {
         let to_append = append_exp.evaluate(self)?.into_owned();
-        let list = self.get_list(list_name)?;
 
-        list.push(to_append);
-        Ok(())
+        match *self.get_path(list_name, path)? {
+            Value::List(ref mut list) => {
+                list.push(to_append);
+                Ok(())
+            }
+            ref val => Err(ErrorKind::IndexUnindexable(val.get_type()).into()),
+        }
     }
 
     fn get_mut(&mut self, name: &str) -> SwResult<&mut Value> {
@@ -203,67 +295,82 @@ impl State {
         }
     }
 
-    fn get_list(&mut self, name: &str) -> SwResult<&mut Vec<Value>> {
-        let value = self.get_mut(name)?;
-        match *value {
-            Value::List(ref mut l) => Ok(l),
-            _ => Err(ErrorKind::IndexUnindexable(value.get_type()).into()),
+    /// Follow a list of indexes into nested lists, like the `[i][j]` in `m[i][j] squanch 0`.
+    fn get_path(&mut self, name: &str, path: &[Expression]) -> SwResult<&mut Value> {
+        let mut indexes = Vec::new();
+        for index_exp in path {
+            indexes.push(index_exp.try_int(self)?);
         }
-    }
-
-    fn get_list_element(&mut self, name: &str, index_exp: &Expression) -> SwResult<&mut Value> {
-        let index = index_exp.try_int(self)? as usize;
-        let value = self.get_mut(name)?;
 
-        match *value {
-            Value::List(ref mut list) => {
-                let len = list.len();
-                if index < len {
-                    Ok(&mut list[index])
-                } else {
-                    Err(ErrorKind::IndexOutOfBounds { len, index }.into())
+        let mut value = self.get_mut(name)?;
+        for index in indexes {
+            value = match *value {
+                Value::List(ref mut list) => {
+                    let index = value::resolve_index(index, list.len())?;
+                    &mut list[index]
                 }
-            }
-            ref val => Err(ErrorKind::IndexUnindexable(val.get_type()).into()),
+                ref val => return Err(ErrorKind::IndexUnindexable(val.get_type()).into()),
+            };
         }
+
+        Ok(value)
     }
 
     fn list_assign(
         &mut self,
         list_name: &str,
-        index_exp: &Expression,
+        path: &[Expression],
         assign_exp: &Expression,
     ) -> SwResult<()> {
         let to_assign = assign_exp.evaluate(self)?.into_owned();
-        let element = self.get_list_element(list_name, index_exp)?;
+        let (index_exp, path) = path
+            .split_last()
+            .ok_or_else(|| ErrorKind::MissingIndex(list_name.to_string()))?;
+        let index = index_exp.try_int(self)?;
 
-        *element = to_assign;
-        Ok(())
-    }
+        match *self.get_path(list_name, path)? {
+            Value::List(ref mut list) => {
+                let index = value::resolve_index(index, list.len())?;
+                list[index] = to_assign;
+                Ok(())
+            }
+            Value::Str(ref mut s) => {
+                let index = value::resolve_index(index, s.char_len())?;
 
-    fn list_delete(&mut self, list_name: &str, index_exp: &Expression) -> SwResult<()> {
-        let index_value = index_exp.evaluate(self)?;
+                match to_assign {
+                    Value::Str(ref replacement) => {
+                        s.splice(index, index + 1, replacement);
+                        Ok(())
+                    }
+                    ref other => Err(ErrorKind::UnexpectedType {
+                        expected: value::Type::Str,
+                        actual: other.get_type(),
+                    }
+                    .into()),
+                }
+            }
+            ref val => Err(ErrorKind::IndexUnindexable(val.get_type()).into()),
+        }
+    }
 
-        if let Value::Int(i) = *index_value {
-            let index = i as usize;
-            let list = self.get_list(list_name)?;
+    fn list_delete(&mut self, list_name: &str, path: &[Expression]) -> SwResult<()> {
+        let (index_exp, path) = path
+            .split_last()
+            .ok_or_else(|| ErrorKind::MissingIndex(list_name.to_string()))?;
+        let index = index_exp.try_int(self)?;
 
-            if index < list.len() {
+        match *self.get_path(list_name, path)? {
+            Value::List(ref mut list) => {
+                let index = value::resolve_index(index, list.len())?;
                 list.remove(index);
                 Ok(())
-            } else {
-                Err(ErrorKind::IndexOutOfBounds {
-                    len: list.len(),
-                    index,
-                }
-                .into())
             }
-        } else {
-            Err(ErrorKind::UnexpectedType {
-                expected: value::Type::Int,
-                actual: index_value.get_type(),
+            Value::Str(ref mut s) => {
+                let index = value::resolve_index(index, s.char_len())?;
+                s.splice(index, index + 1, "");
+                Ok(())
             }
-            .into())
+            ref val => Err(ErrorKind::IndexUnindexable(val.get_type()).into()),
         }
     }
 
@@ -319,6 +426,8 @@ impl State {
 
     fn catch(&mut self, try_block: &[Statement], catch: &[Statement]) -> SwResult<()> {
         match self.run(try_block) {
+            // Asking to exit isn't a failure, so there's nothing to catch
+            Err(e) if matches!(e.kind(), ErrorKind::Exit(_)) => return Err(e.into()),
             Err(_) => self.run(catch)?,
             _ => {}
         }
@@ -327,13 +436,28 @@ impl State {
     }
 
     pub fn execute(&mut self, statement: &Statement) -> Result<(), ErrorWithContext> {
+        // The hook is taken out while it runs so that anything it evaluates isn't hooked as well
+        if let Some(hooked) = self.hook.take() {
+            hooked.hook.borrow_mut().statement(self, statement);
+
+            if let StatementKind::Function(ref name, _, _) = statement.kind {
+                hooked.functions.borrow_mut().insert(name.clone());
+            }
+
+            self.hook = Some(hooked);
+        }
+
         match statement.kind {
-            StatementKind::Input(ref s) => self.input(s.to_string()),
-            StatementKind::ListAssign(ref s, ref index_exp, ref assign_exp) => {
-                self.list_assign(s, index_exp, assign_exp)
+            StatementKind::Input(ref s) => self.input(s.to_string(), true),
+            StatementKind::InputRaw(ref s) => self.input(s.to_string(), false),
+            StatementKind::InputAll(ref s) => self.input_all(s.to_string()),
+            StatementKind::ListAssign(ref s, ref path, ref assign_exp) => {
+                self.list_assign(s, path, assign_exp)
+            }
+            StatementKind::ListAppend(ref s, ref path, ref append_exp) => {
+                self.list_append(s, path, append_exp)
             }
-            StatementKind::ListAppend(ref s, ref append_exp) => self.list_append(s, append_exp),
-            StatementKind::ListDelete(ref name, ref idx) => self.list_delete(name, idx),
+            StatementKind::ListDelete(ref name, ref path) => self.list_delete(name, path),
             StatementKind::ListNew(ref s) => {
                 self.symbols.insert(s.clone(), Value::List(Vec::new()));
 
@@ -345,8 +469,11 @@ impl State {
             StatementKind::While(ref bool, ref body) => self.exec_while(statement, bool, body),
             StatementKind::Assignment(ref name, ref value) => self.assign(name.clone(), value),
             StatementKind::Delete(ref name) => self.delete(name),
-            StatementKind::Print(ref exp) => self.print(exp),
-            StatementKind::PrintNoNl(ref exp) => self.print_no_nl(exp),
+            StatementKind::Print(ref exp) => self.print(&self.output, exp, true),
+            StatementKind::PrintNoNl(ref exp) => self.print(&self.output, exp, false),
+            StatementKind::PrintErr(ref exp) => self.print(&self.error_output, exp, true),
+            StatementKind::PrintErrNoNl(ref exp) => self.print(&self.error_output, exp, false),
+            StatementKind::Assert(ref exp) => self.assert(exp),
             StatementKind::Catch(ref try_block, ref catch) => self.catch(try_block, catch),
             StatementKind::Function(ref name, ref args, ref body) => {
                 self.symbols
@@ -388,6 +515,10 @@ impl State {
     }
 
     fn dylib_load(&mut self, lib_path: &str, functions: &[Statement]) -> SwResult<()> {
+        if !self.allows(Capability::NativeCode) {
+            return Err(ErrorKind::Sandboxed(lib_path.into()).into());
+        }
+
         unsafe {
             let dylib = libloading::Library::new(lib_path)?;
 
@@ -432,6 +563,12 @@ impl State {
         self.symbols.insert("argv".into(), value_args.into());
     }
 
+    /// Let the program see the name it was run as through `program`.
+    pub fn set_program(&mut self, filename: &str) {
+        self.symbols
+            .insert("program".into(), filename.to_string().into());
+    }
+
     pub fn insert<S, V>(&mut self, name: S, value: V)
     where
         S: Into<String>,
@@ -440,9 +577,58 @@ impl State {
         self.symbols.insert(name.into(), value.into());
     }
 
+    pub fn variables(&self) -> impl Iterator<Item = (&str, &Value)> {
+        self.symbols
+            .iter()
+            .map(|(name, value)| (name.as_str(), value))
+    }
+
+    /// Stop the program from doing something, like running other programs. Anything it calls
+    /// that needs `capability` fails from now on.
+    pub fn deny(&mut self, capability: Capability) {
+        Rc::make_mut(&mut self.denied).insert(capability);
+    }
+
+    /// Deny everything that isn't safe for untrusted code.
+    pub fn sandbox(&mut self) {
+        self.deny(Capability::Subprocess);
+        self.deny(Capability::NativeCode);
+        self.deny(Capability::WriteFiles);
+    }
+
+    pub fn allows(&self, capability: Capability) -> bool {
+        !self.denied.contains(&capability)
+    }
+
+    /// Let floats be divided by zero, giving infinity or NaN like IEEE 754 says instead of failing.
+    pub fn set_ieee_floats(&mut self, ieee_floats: bool) {
+        self.ieee_floats = ieee_floats;
+    }
+
+    pub fn ieee_floats(&self) -> bool {
+        self.ieee_floats
+    }
+
+    /// Install a hook that will be told about every statement run from now on.
+    pub fn set_hook(&mut self, hook: Rc<RefCell<dyn Hook>>) {
+        self.hook = Some(Hooked {
+            hook,
+            functions: Default::default(),
+        });
+    }
+
     pub fn new() -> Self {
         Self::default()
     }
+
+    /// A state that reads input from `input` and prints to `output` instead of stdin and stdout.
+    pub fn with_io(input: Rc<RefCell<dyn BufRead>>, output: Rc<RefCell<dyn Write>>) -> Self {
+        Self {
+            input,
+            output,
+            ..Self::default()
+        }
+    }
 }
 
 impl Default for State {
@@ -451,6 +637,12 @@ impl Default for State {
             symbols: Map::new(),
             last_return: None,
             libraries: Vec::new(),
+            hook: None,
+            input: Rc::new(RefCell::new(io::BufReader::new(io::stdin()))),
+            output: Rc::new(RefCell::new(io::stdout())),
+            error_output: Rc::new(RefCell::new(io::stderr())),
+            denied: Default::default(),
+            ieee_floats: false,
         }
     }
 }
@@ -1,10 +1,18 @@
 use crate::{
+    builtins,
     error::ErrorKind as EKind,
     expression::Expression as Exp,
     grammar,
     state::State,
     statement::{Statement, StatementKind as Kind},
-    value::Value,
+    value::{Type, Value},
+    Operator as Op,
+};
+use std::{
+    cell::RefCell,
+    env, fs,
+    io::{self, Cursor},
+    rc::Rc,
 };
 
 #[test]
@@ -124,6 +132,12 @@ fn test_early_return_if() {
     assert_eq!(*state.get("z").unwrap(), Value::new(false));
     assert_eq!(*state.get("a").unwrap(), Value::new(false));
     assert_eq!(*state.get("b").unwrap(), Value::new(true));
+
+    let call = grammar::statement("small(1, 2)").unwrap();
+    assert_eq!(
+        state.execute(&call).unwrap_err().kind(),
+        &EKind::InvalidArguments("small".into(), 1, 2)
+    );
 }
 
 #[test]
@@ -157,3 +171,1053 @@ fn test_early_return_while() {
     assert_eq!(*state.get("a").unwrap(), Value::new(false));
     assert_eq!(*state.get("b").unwrap(), Value::new(true));
 }
+
+#[test]
+fn test_assert() {
+    let mut state = State::new();
+
+    let passing = grammar::statement("wubba lubba dub dub (1 == 1)").unwrap();
+    state.execute(&passing).unwrap();
+
+    let failing = grammar::statement("wubba lubba dub dub (1 == 2)").unwrap();
+    assert_eq!(
+        state.execute(&failing).unwrap_err().kind(),
+        &EKind::AssertionFailed
+    );
+
+    let not_bool = grammar::statement("wubba lubba dub dub 1").unwrap();
+    assert_eq!(
+        state.execute(&not_bool).unwrap_err().kind(),
+        &EKind::UnexpectedType {
+            expected: Type::Bool,
+            actual: Type::Int,
+        }
+    );
+}
+
+#[test]
+fn test_captured_io() {
+    let input = Rc::new(RefCell::new(Cursor::new("Nate\n")));
+    let output = Rc::new(RefCell::new(Vec::new()));
+
+    let mut state = State::with_io(input, output.clone());
+
+    // The same way a host program would, since it can't get at the grammar
+    let code = crate::parse(
+        r#"
+    greet(name) :<
+        show me what you got! "Hello, "
+        show me what you got name
+        return rick
+    >:
+
+    show me what you got! "Enter your name: "
+    portal gun name
+    greet(name)
+    "#,
+    )
+    .unwrap();
+
+    state.run(&code).unwrap();
+    assert_eq!(
+        String::from_utf8(output.borrow().clone()).unwrap(),
+        "Enter your name: Hello, Nate\n"
+    );
+
+    match crate::parse("greet(").unwrap_err() {
+        EKind::SyntaxError(_) => {}
+        kind => panic!("expected a SyntaxError, got {:?}", kind),
+    }
+}
+
+#[test]
+fn test_set_output() {
+    let first = Rc::new(RefCell::new(Vec::new()));
+    let second = Rc::new(RefCell::new(Vec::new()));
+
+    let mut state = State::new();
+    state.set_input(Rc::new(RefCell::new(io::empty())));
+
+    state.set_output(first.clone());
+    let print = grammar::statement("show me what you got 1").unwrap();
+    state.execute(&print).unwrap();
+
+    state.set_output(second.clone());
+    let print = grammar::statement("show me what you got 2").unwrap();
+    state.execute(&print).unwrap();
+
+    let input = grammar::statement("portal gun x").unwrap();
+    state.execute(&input).unwrap();
+
+    assert_eq!(*first.borrow(), b"1\n");
+    assert_eq!(*second.borrow(), b"2\n");
+    assert_eq!(*state.get("x").unwrap(), Value::new(false));
+}
+
+#[test]
+fn test_print_err() {
+    let output = Rc::new(RefCell::new(Vec::new()));
+    let errors = Rc::new(RefCell::new(Vec::new()));
+
+    let mut state = State::with_io(Rc::new(RefCell::new(io::empty())), output.clone());
+    state.set_error_output(errors.clone());
+
+    let code = grammar::file(
+        r#"
+    show me what you got "out"
+    existence is pain! "oh "
+    existence is pain "no"
+    "#,
+    )
+    .unwrap();
+
+    state.run(&code).unwrap();
+    assert_eq!(*output.borrow(), b"out\n");
+    assert_eq!(*errors.borrow(), b"oh no\n");
+}
+
+#[test]
+fn test_format() {
+    let mut state = State::new();
+
+    let code = grammar::file(
+        r#"
+    row on a cob
+    row assimilate "pi"
+    row assimilate 1.23456
+    x squanch ("{:<4}|{:>7.2}" % row)
+    y squanch ("{:03}" % 7)
+    "#,
+    )
+    .unwrap();
+
+    state.run(&code).unwrap();
+    assert_eq!(*state.get("x").unwrap(), Value::new("pi  |   1.23"));
+    assert_eq!(*state.get("y").unwrap(), Value::new("007"));
+}
+
+#[test]
+fn test_ascii_quotes() {
+    let mut state = State::new();
+    builtins::load(&mut state);
+
+    let code = grammar::file(
+        r#"
+    quote squanch ascii(34)
+    backslash squanch ascii(92)
+    "#,
+    )
+    .unwrap();
+    state.run(&code).unwrap();
+
+    assert_eq!(state.get("quote").unwrap(), &Value::new("\""));
+    assert_eq!(state.get("backslash").unwrap(), &Value::new("\\"));
+}
+
+#[test]
+fn test_unicode_strings() {
+    let mut state = State::new();
+
+    let code = grammar::file(
+        r#"
+    word squanch "pickle ríck 🥒"
+    size squanch (word squanch)
+    accent squanch word[8]
+    last squanch word[(size - 1)]
+    "#,
+    )
+    .unwrap();
+    state.run(&code).unwrap();
+
+    assert_eq!(state.get("size").unwrap(), &Value::new(13));
+    assert_eq!(state.get("accent").unwrap(), &Value::new("í"));
+    assert_eq!(state.get("last").unwrap(), &Value::new("🥒"));
+
+    let past_end = grammar::statement("x squanch word[13]").unwrap();
+    assert_eq!(
+        state.execute(&past_end).unwrap_err().kind(),
+        &EKind::IndexOutOfBounds { len: 13, index: 13 }
+    );
+}
+
+#[test]
+fn test_slices() {
+    let mut state = State::new();
+
+    let code = grammar::file(
+        r#"
+    word squanch "pickle ríck"
+    nums on a cob
+    nums assimilate 0
+    nums assimilate 1
+    nums assimilate 2
+    nums assimilate 3
+    nums assimilate 4
+
+    first squanch word[:6]
+    last squanch word[-4:]
+    reversed squanch word[::-1]
+    evens squanch nums[::2]
+    middle squanch nums[1:-1]
+    backwards squanch nums[3:0:-1]
+    clamped squanch nums[-100:100]
+    empty squanch nums[3:1]
+    "#,
+    )
+    .unwrap();
+    state.run(&code).unwrap();
+
+    let list =
+        |items: &[i64]| Value::new(items.iter().copied().map(Value::Int).collect::<Vec<_>>());
+
+    assert_eq!(state.get("first").unwrap(), &Value::new("pickle"));
+    assert_eq!(state.get("last").unwrap(), &Value::new("ríck"));
+    assert_eq!(state.get("reversed").unwrap(), &Value::new("kcír elkcip"));
+    assert_eq!(state.get("evens").unwrap(), &list(&[0, 2, 4]));
+    assert_eq!(state.get("middle").unwrap(), &list(&[1, 2, 3]));
+    assert_eq!(state.get("backwards").unwrap(), &list(&[3, 2, 1]));
+    assert_eq!(state.get("clamped").unwrap(), &list(&[0, 1, 2, 3, 4]));
+    assert_eq!(state.get("empty").unwrap(), &list(&[]));
+
+    let zero_step = grammar::statement("x squanch nums[::0]").unwrap();
+    assert_eq!(
+        state.execute(&zero_step).unwrap_err().kind(),
+        &EKind::ZeroStep
+    );
+
+    for (code, expected) in &[
+        ("x squanch nums[1::9223372036854775807]", list(&[1])),
+        ("x squanch nums[-2::-9223372036854775807]", list(&[3])),
+    ] {
+        let statement = grammar::statement(code).unwrap();
+        state.execute(&statement).unwrap();
+        assert_eq!(state.get("x").unwrap(), expected, "{}", code);
+    }
+}
+
+#[test]
+fn test_negative_indexes() {
+    let mut state = State::new();
+
+    let code = grammar::file(
+        r#"
+    word squanch "ríck"
+    nums on a cob
+    nums assimilate 1
+    nums assimilate 2
+    nums assimilate 3
+
+    last squanch nums[-1]
+    first squanch nums[-3]
+    letter squanch word[-3]
+    nums[-1] squanch 30
+    squanch nums[-3]
+    squanch word[-1]
+    "#,
+    )
+    .unwrap();
+    state.run(&code).unwrap();
+
+    assert_eq!(state.get("last").unwrap(), &Value::new(3));
+    assert_eq!(state.get("first").unwrap(), &Value::new(1));
+    assert_eq!(state.get("letter").unwrap(), &Value::new("í"));
+    assert_eq!(
+        state.get("nums").unwrap(),
+        &Value::new(vec![Value::new(2), Value::new(30)])
+    );
+    assert_eq!(state.get("word").unwrap(), &Value::new("ríc"));
+
+    for code in &[
+        "x squanch nums[-3]",
+        "nums[-3] squanch 1",
+        "squanch nums[-3]",
+    ] {
+        let statement = grammar::statement(code).unwrap();
+        assert_eq!(
+            state.execute(&statement).unwrap_err().kind(),
+            &EKind::IndexOutOfBounds { len: 2, index: -3 }
+        );
+    }
+
+    let too_far = grammar::statement("squanch word[-4]").unwrap();
+    assert_eq!(
+        state.execute(&too_far).unwrap_err().kind().to_string(),
+        "Y-you can't just keep asking for more, Morty! You want -4, but your cob only has 3 kernels on it!"
+    );
+}
+
+#[test]
+fn test_nested_lists() {
+    let mut state = State::new();
+
+    let code = grammar::file(
+        r#"
+    grid (rows, columns) :<
+        m on a cob
+        while (rows more 0) :<
+            row on a cob
+            i squanch 0
+            while (i less columns) :<
+                row assimilate i
+                i squanch (i + 1)
+            >:
+            m assimilate row
+            rows squanch (rows - 1)
+        >:
+        return m
+    >:
+
+    m squanch grid(2, 3)
+    m[1][2] squanch "x"
+    m[0] assimilate 3
+    squanch m[1][0]
+    corner squanch m[-1][-1]
+    called squanch grid(3, 2)[2][1]
+    letter squanch m[1][-1][0]
+    "#,
+    )
+    .unwrap();
+    state.run(&code).unwrap();
+
+    let row = |items: Vec<Value>| Value::new(items);
+    assert_eq!(
+        state.get("m").unwrap(),
+        &row(vec![
+            row(vec![0.into(), 1.into(), 2.into(), 3.into()]),
+            row(vec![1.into(), "x".into()]),
+        ])
+    );
+    assert_eq!(state.get("corner").unwrap(), &Value::new("x"));
+    assert_eq!(state.get("called").unwrap(), &Value::new(1));
+    assert_eq!(state.get("letter").unwrap(), &Value::new("x"));
+
+    let too_deep = grammar::statement("m[0][0][0] squanch 1").unwrap();
+    assert_eq!(
+        state.execute(&too_deep).unwrap_err().kind(),
+        &EKind::IndexUnindexable(Type::Int)
+    );
+
+    let out_of_bounds = grammar::statement("m[2][0] squanch 1").unwrap();
+    assert_eq!(
+        state.execute(&out_of_bounds).unwrap_err().kind(),
+        &EKind::IndexOutOfBounds { len: 2, index: 2 }
+    );
+
+    // The grammar always gives at least one index, but statements can be built by hand too
+    for kind in &[
+        Kind::ListAssign("m".into(), Vec::new(), Exp::Value(1.into())),
+        Kind::ListDelete("m".into(), Vec::new()),
+    ] {
+        assert_eq!(
+            state
+                .execute(&Statement::tnew(kind.clone()))
+                .unwrap_err()
+                .kind(),
+            &EKind::MissingIndex("m".into())
+        );
+    }
+}
+
+#[test]
+fn test_list_literals() {
+    let mut state = State::new();
+
+    let code = grammar::file(
+        r#"
+    pair (a, b) :<
+        return [a, b]
+    >:
+
+    x squanch 2
+    nested squanch [1, [x, (x * 2)], []]
+    joined squanch (pair(1, 2) + [3])
+    repeated squanch ([0, "a"] * 2)
+    none squanch ([1] * 0)
+    text squanch ("ab" * 3)
+    no_text squanch ("ab" * 0)
+    negative_text squanch ("ab" * -1)
+    "#,
+    )
+    .unwrap();
+    state.run(&code).unwrap();
+
+    let list = |items: Vec<Value>| Value::new(items);
+    assert_eq!(
+        state.get("nested").unwrap(),
+        &list(vec![1.into(), list(vec![2.into(), 4.into()]), list(vec![])])
+    );
+    assert_eq!(
+        state.get("joined").unwrap(),
+        &list(vec![1.into(), 2.into(), 3.into()])
+    );
+    assert_eq!(
+        state.get("repeated").unwrap(),
+        &list(vec![0.into(), "a".into(), 0.into(), "a".into()])
+    );
+    assert_eq!(state.get("none").unwrap(), &list(vec![]));
+    assert_eq!(state.get("text").unwrap(), &Value::new("ababab"));
+    assert_eq!(state.get("no_text").unwrap(), &Value::new(""));
+    assert_eq!(state.get("negative_text").unwrap(), &Value::new(""));
+}
+
+#[test]
+fn test_integer_overflow() {
+    let mut state = State::new();
+    state.insert("max", i64::MAX);
+    state.insert("min", i64::MIN);
+
+    for (code, op) in &[
+        ("x squanch (max + 1)", Op::Add),
+        ("x squanch (min - 1)", Op::Subtract),
+        ("x squanch (max * 2)", Op::Multiply),
+        ("x squanch ([1, 2] * max)", Op::Multiply),
+        ("x squanch (\"ab\" * max)", Op::Multiply),
+        // Small enough to count, but far too big to fit in memory
+        ("x squanch (\"ab\" * 1000000000000000000)", Op::Multiply),
+        ("x squanch ([1, 2] * 100000000000000000)", Op::Multiply),
+        ("x squanch (1 <schwift 63)", Op::ShiftLeft),
+        ("x squanch (min <schwift 1)", Op::ShiftLeft),
+        ("x squanch (3 <schwift 62)", Op::ShiftLeft),
+    ] {
+        let statement = grammar::statement(code).unwrap();
+        assert_eq!(
+            state.execute(&statement).unwrap_err().kind(),
+            &EKind::Overflow(op.clone()),
+            "{}",
+            code
+        );
+    }
+
+    for (code, amount) in &[
+        ("x squanch (1 <schwift 64)", 64),
+        ("x squanch (1 schwift> -1)", -1),
+    ] {
+        let statement = grammar::statement(code).unwrap();
+        assert_eq!(
+            state.execute(&statement).unwrap_err().kind(),
+            &EKind::InvalidShift(*amount),
+            "{}",
+            code
+        );
+    }
+
+    let code = grammar::file(
+        r#"
+    biggest squanch ((max - 1) + 1)
+    sign squanch (-1 <schwift 62)
+    top squanch (max schwift> 62)
+    empty squanch ("" * max)
+    normal plan :<
+        x squanch (max + max)
+        caught squanch morty
+    >: plan for failure :<
+        caught squanch rick
+    >:
+    "#,
+    )
+    .unwrap();
+    state.run(&code).unwrap();
+
+    assert_eq!(state.get("biggest").unwrap(), &Value::new(i64::MAX));
+    assert_eq!(state.get("sign").unwrap(), &Value::new(-1 << 62));
+    assert_eq!(state.get("top").unwrap(), &Value::new(1));
+    assert_eq!(state.get("empty").unwrap(), &Value::new(""));
+    assert_eq!(state.get("caught").unwrap(), &Value::new(true));
+}
+
+#[test]
+fn test_division_by_zero() {
+    let mut state = State::new();
+    state.insert("min", i64::MIN);
+
+    for (code, op) in &[
+        ("x squanch (1 / 0)", Op::Divide),
+        ("x squanch (1 % 0)", Op::Modulus),
+        ("x squanch (1.5 / 0.0)", Op::Divide),
+        ("x squanch (1 / 0.0)", Op::Divide),
+        ("x squanch (0.0 / 0)", Op::Divide),
+    ] {
+        let statement = grammar::statement(code).unwrap();
+        assert_eq!(
+            state.execute(&statement).unwrap_err().kind(),
+            &EKind::DivisionByZero(op.clone()),
+            "{}",
+            code
+        );
+    }
+
+    let overflow = grammar::statement("x squanch (min / -1)").unwrap();
+    assert_eq!(
+        state.execute(&overflow).unwrap_err().kind(),
+        &EKind::Overflow(Op::Divide)
+    );
+
+    let code = grammar::file(
+        r#"
+    remainder squanch (min % -1)
+    normal plan :<
+        x squanch (10 / 0)
+        caught squanch morty
+    >: plan for failure :<
+        caught squanch rick
+    >:
+    "#,
+    )
+    .unwrap();
+    state.run(&code).unwrap();
+
+    assert_eq!(state.get("remainder").unwrap(), &Value::new(0));
+    assert_eq!(state.get("caught").unwrap(), &Value::new(true));
+}
+
+#[test]
+fn test_ieee_floats() {
+    let mut state = State::new();
+    state.set_ieee_floats(true);
+
+    let code = grammar::file(
+        r#"
+    up squanch (1.0 / 0)
+    down squanch (-1 / 0.0)
+    nan squanch (0.0 / 0.0)
+    halve (x) :<
+        return (x / 0.0)
+    >:
+    called squanch halve(2)
+    "#,
+    )
+    .unwrap();
+    state.run(&code).unwrap();
+
+    let float = |name| match *state.get(name).unwrap() {
+        Value::Float(f) => f,
+        ref other => panic!("{} is a {}", name, other.get_type()),
+    };
+
+    assert_eq!(float("up"), f64::INFINITY);
+    assert_eq!(float("down"), f64::NEG_INFINITY);
+    assert!(float("nan").is_nan());
+    assert_eq!(float("called"), f64::INFINITY);
+
+    // Ints have nothing to give back, so they still fail
+    let ints = grammar::statement("x squanch (1 / 0)").unwrap();
+    assert_eq!(
+        state.execute(&ints).unwrap_err().kind(),
+        &EKind::DivisionByZero(Op::Divide)
+    );
+}
+
+#[test]
+fn test_comparisons() {
+    let mut state = State::new();
+    state.set_ieee_floats(true);
+    state.insert("big", 9_007_199_254_740_993_i64);
+
+    let code = grammar::file(
+        r#"
+    nan squanch (0.0 / 0.0)
+    strings squanch ("apple" less "banana")
+    prefix squanch ("app" less "apple")
+    lists squanch ([1, 2, 3] less [1, 3])
+    shorter squanch ([1, 2] lesssquanch [1, 2])
+    mixed squanch (2 more 1.5)
+    exact squanch (9007199254740992.0 == big)
+    below squanch (9007199254740992.0 less big)
+    tiny squanch (0.0000000000000001 == 0.0)
+    whole squanch (3 == 3.0)
+    nan_equal squanch (nan == nan)
+    nan_less squanch (nan less 1)
+    nan_more squanch (nan moresquanch 1)
+    nan_list squanch ([nan] less [1])
+    "#,
+    )
+    .unwrap();
+    state.run(&code).unwrap();
+
+    for (name, expected) in &[
+        ("strings", true),
+        ("prefix", true),
+        ("lists", true),
+        ("shorter", true),
+        ("mixed", true),
+        ("exact", false),
+        ("below", true),
+        ("tiny", false),
+        ("whole", true),
+        ("nan_equal", false),
+        ("nan_less", false),
+        ("nan_more", false),
+        ("nan_list", false),
+    ] {
+        assert_eq!(state.get(name).unwrap(), &Value::new(*expected), "{}", name);
+    }
+
+    for (code, left, right) in &[
+        ("x squanch (\"1\" less 2)", Type::Str, Type::Int),
+        ("x squanch ([1] less [\"a\"])", Type::Int, Type::Str),
+        ("x squanch ([] less 1)", Type::List, Type::Int),
+    ] {
+        let statement = grammar::statement(code).unwrap();
+        assert_eq!(
+            state.execute(&statement).unwrap_err().kind(),
+            &EKind::InvalidBinaryExpression(left.clone(), right.clone(), Op::LessThan),
+            "{}",
+            code
+        );
+    }
+}
+
+#[test]
+fn test_integer_operators() {
+    let mut state = State::new();
+    state.insert("min", i64::MIN);
+
+    let code = grammar::file(
+        r#"
+    and squanch (12 & 10)
+    or squanch (12 | 10)
+    xor squanch (12 ^ 10)
+    not squanch !5
+    power squanch (2 ** 10)
+    huge squanch (-1 ** 5000000000)
+    negative squanch (2 ** -1)
+    float_power squanch (4.0 ** 0.5)
+    floor squanch (7 // 2)
+    floor_negative squanch (-7 // 2)
+    floor_divisor squanch (7 // -2)
+    floor_exact squanch (-6 // 2)
+    floor_float squanch (-7 // 2.0)
+    "#,
+    )
+    .unwrap();
+    state.run(&code).unwrap();
+
+    for (name, expected) in &[
+        ("and", Value::new(8)),
+        ("or", Value::new(14)),
+        ("xor", Value::new(6)),
+        ("not", Value::new(-6)),
+        ("power", Value::new(1024)),
+        ("huge", Value::new(1)),
+        ("negative", Value::new(0.5)),
+        ("float_power", Value::new(2.0)),
+        ("floor", Value::new(3)),
+        ("floor_negative", Value::new(-4)),
+        ("floor_divisor", Value::new(-4)),
+        ("floor_exact", Value::new(-3)),
+        ("floor_float", Value::new(-4.0)),
+    ] {
+        assert_eq!(state.get(name).unwrap(), expected, "{}", name);
+    }
+
+    for (code, error) in &[
+        (
+            "x squanch (1 & 1.0)",
+            EKind::InvalidBinaryExpression(Type::Int, Type::Float, Op::BitAnd),
+        ),
+        (
+            "x squanch (rick | morty)",
+            EKind::InvalidBinaryExpression(Type::Bool, Type::Bool, Op::BitOr),
+        ),
+        (
+            "x squanch (\"a\" ^ 1)",
+            EKind::InvalidBinaryExpression(Type::Str, Type::Int, Op::BitXor),
+        ),
+        (
+            "x squanch (\"a\" ** 2)",
+            EKind::InvalidBinaryExpression(Type::Str, Type::Int, Op::Power),
+        ),
+        (
+            "x squanch ([] // 2)",
+            EKind::InvalidBinaryExpression(Type::List, Type::Int, Op::FloorDivide),
+        ),
+        ("x squanch (2 ** 63)", EKind::Overflow(Op::Power)),
+        ("x squanch (3 ** 5000000000)", EKind::Overflow(Op::Power)),
+        ("x squanch (min // -1)", EKind::Overflow(Op::FloorDivide)),
+        ("x squanch (1 // 0)", EKind::DivisionByZero(Op::FloorDivide)),
+        (
+            "x squanch (1.0 // 0)",
+            EKind::DivisionByZero(Op::FloorDivide),
+        ),
+        ("x squanch (0 ** -1)", EKind::DivisionByZero(Op::Power)),
+    ] {
+        let statement = grammar::statement(code).unwrap();
+        assert_eq!(
+            state.execute(&statement).unwrap_err().kind(),
+            error,
+            "{}",
+            code
+        );
+    }
+}
+
+#[test]
+fn test_string_mutation() {
+    let mut state = State::new();
+
+    let code = grammar::file(
+        r#"
+    word squanch "pickle ríck"
+    word[0] squanch "t"
+    word[8] squanch "i"
+    squanch word[6]
+    "#,
+    )
+    .unwrap();
+    state.run(&code).unwrap();
+
+    assert_eq!(state.get("word").unwrap(), &Value::new("ticklerick"));
+
+    let past_end = grammar::statement("word[10] squanch \"s\"").unwrap();
+    assert_eq!(
+        state.execute(&past_end).unwrap_err().kind(),
+        &EKind::IndexOutOfBounds { len: 10, index: 10 }
+    );
+
+    let past_end = grammar::statement("squanch word[10]").unwrap();
+    assert_eq!(
+        state.execute(&past_end).unwrap_err().kind(),
+        &EKind::IndexOutOfBounds { len: 10, index: 10 }
+    );
+
+    let not_string = grammar::statement("word[0] squanch 1").unwrap();
+    assert_eq!(
+        state.execute(&not_string).unwrap_err().kind(),
+        &EKind::UnexpectedType {
+            expected: Type::Str,
+            actual: Type::Int,
+        }
+    );
+}
+
+#[test]
+fn test_file_builtins() {
+    let dir = env::temp_dir().join("schwift-file-builtins");
+    fs::remove_dir_all(&dir).ok();
+    fs::create_dir_all(&dir).unwrap();
+
+    let mut state = State::new();
+    builtins::load(&mut state);
+    state.insert("dir", dir.to_str().unwrap().to_string());
+    state.insert("path", dir.join("portal.txt").to_str().unwrap().to_string());
+
+    let code = grammar::file(
+        r#"
+    before squanch fileExists(path)
+    writeFile(path, "wubba")
+    appendFile(path, "\n")
+    appendFile(path, 42)
+    after squanch fileExists(path)
+    contents squanch readFile(path)
+    lines squanch readLines(path)
+    files squanch listDir(dir)
+    "#,
+    )
+    .unwrap();
+
+    state.run(&code).unwrap();
+    assert_eq!(*state.get("before").unwrap(), Value::new(false));
+    assert_eq!(*state.get("after").unwrap(), Value::new(true));
+    assert_eq!(*state.get("contents").unwrap(), Value::new("wubba\n42"));
+    assert_eq!(
+        *state.get("lines").unwrap(),
+        Value::new(vec!["wubba", "42"])
+    );
+    assert_eq!(*state.get("files").unwrap(), Value::new(vec!["portal.txt"]));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_native_builtins_in_functions() {
+    let path = env::temp_dir().join("schwift-native-in-function.txt");
+
+    let mut state = State::new();
+    builtins::load(&mut state);
+    state.insert("path", path.to_str().unwrap().to_string());
+
+    let code = grammar::file(
+        r#"
+    save(path, text) :<
+        writeFile(path, text)
+        return readFile(path)
+    >:
+
+    leave(code) :<
+        exit(code)
+        return morty
+    >:
+
+    contents squanch save(path, "squanch")
+    "#,
+    )
+    .unwrap();
+
+    state.run(&code).unwrap();
+    assert_eq!(*state.get("contents").unwrap(), Value::new("squanch"));
+
+    let exit = grammar::statement("leave(3)").unwrap();
+    assert_eq!(state.execute(&exit).unwrap_err().kind(), &EKind::Exit(3));
+
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_file_builtin_errors_can_be_caught() {
+    let mut state = State::new();
+    builtins::load(&mut state);
+
+    let code = grammar::file(
+        r#"
+    normal plan :<
+        contents squanch readFile("/there/is/no/such/file")
+        caught squanch morty
+    >: plan for failure :<
+        caught squanch rick
+    >:
+    "#,
+    )
+    .unwrap();
+
+    state.run(&code).unwrap();
+    assert_eq!(*state.get("caught").unwrap(), Value::new(true));
+
+    let read =
+        grammar::statement(r#"contents squanch readFile("/there/is/no/such/file")"#).unwrap();
+    match state.execute(&read).unwrap_err().kind() {
+        EKind::IOError(_) => {}
+        kind => panic!("expected an IOError, got {:?}", kind),
+    }
+
+    let call = grammar::statement("fileExists(1, 2)").unwrap();
+    assert_eq!(
+        state.execute(&call).unwrap_err().kind(),
+        &EKind::InvalidArguments("fileExists".into(), 1, 2)
+    );
+}
+
+#[test]
+fn test_input_at_eof() {
+    let input = Rc::new(RefCell::new(Cursor::new("  first  \n\nlast")));
+    let mut state = State::with_io(input, Rc::new(RefCell::new(io::sink())));
+
+    let code = grammar::file(
+        r#"
+    portal gun raw a
+    portal gun b
+    portal gun c
+    portal gun d
+    "#,
+    )
+    .unwrap();
+
+    state.run(&code).unwrap();
+    assert_eq!(*state.get("a").unwrap(), Value::new("  first  \n"));
+    assert_eq!(*state.get("b").unwrap(), Value::new(""));
+    assert_eq!(*state.get("c").unwrap(), Value::new("last"));
+    assert_eq!(*state.get("d").unwrap(), Value::new(false));
+}
+
+#[test]
+fn test_input_all() {
+    let input = Rc::new(RefCell::new(Cursor::new("one\ntwo\nthree\n")));
+    let mut state = State::with_io(input, Rc::new(RefCell::new(io::sink())));
+
+    let code = grammar::file(
+        r#"
+    portal gun first
+    portal gun all rest
+    portal gun all nothing
+    "#,
+    )
+    .unwrap();
+
+    state.run(&code).unwrap();
+    assert_eq!(*state.get("first").unwrap(), Value::new("one"));
+    assert_eq!(*state.get("rest").unwrap(), Value::new("two\nthree\n"));
+    assert_eq!(*state.get("nothing").unwrap(), Value::new(""));
+}
+
+#[test]
+fn test_exit_is_not_caught() {
+    let mut state = State::new();
+    builtins::load(&mut state);
+
+    let code = grammar::file(
+        r#"
+    normal plan :<
+        exit(4)
+    >: plan for failure :<
+        caught squanch rick
+    >:
+    "#,
+    )
+    .unwrap();
+
+    assert_eq!(state.run(&code).unwrap_err().kind(), &EKind::Exit(4));
+    assert!(state.get("caught").is_err());
+}
+
+#[test]
+fn test_exit_status_out_of_range() {
+    let mut state = State::new();
+    builtins::load(&mut state);
+
+    let code = grammar::file(
+        r#"
+    normal plan :<
+        exit(4294967296)
+    >: plan for failure :<
+        caught squanch rick
+    >:
+    "#,
+    )
+    .unwrap();
+
+    state.run(&code).unwrap();
+    assert_eq!(*state.get("caught").unwrap(), Value::new(true));
+
+    let exit = grammar::statement("exit(-2147483649)").unwrap();
+    assert_eq!(
+        state.execute(&exit).unwrap_err().kind(),
+        &EKind::InvalidExitStatus(-2_147_483_649)
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_run_process() {
+    let mut state = State::new();
+    builtins::load(&mut state);
+
+    let code = grammar::file(
+        r#"
+    args on a cob
+    args assimilate "-c"
+    args assimilate "echo out; echo err >&2; exit 3"
+    result squanch runProcess("sh", args)
+
+    args on a cob
+    cat squanch runProcessWith("cat", args, "wubba lubba", morty)
+    pwd squanch runProcessWith("pwd", args, morty, "/")
+    "#,
+    )
+    .unwrap();
+
+    state.run(&code).unwrap();
+    assert_eq!(
+        *state.get("result").unwrap(),
+        Value::List(vec![
+            Value::new("out\n"),
+            Value::new("err\n"),
+            Value::new(3)
+        ])
+    );
+    assert_eq!(
+        *state.get("cat").unwrap(),
+        Value::List(vec![
+            Value::new("wubba lubba"),
+            Value::new(""),
+            Value::new(0)
+        ])
+    );
+    assert_eq!(
+        *state.get("pwd").unwrap(),
+        Value::List(vec![Value::new("/\n"), Value::new(""), Value::new(0)])
+    );
+}
+
+#[test]
+fn test_sandbox() {
+    let mut state = State::new();
+    builtins::load(&mut state);
+    state.sandbox();
+
+    let code = grammar::file(
+        r#"
+    args on a cob
+    result squanch runProcess("ls", args)
+    "#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        state.run(&code).unwrap_err().kind(),
+        &EKind::Sandboxed("runProcess".into())
+    );
+
+    for (code, name) in &[
+        (r#"writeFile("portal.txt", 1)"#, "writeFile"),
+        (r#"appendFile("portal.txt", 1)"#, "appendFile"),
+        (
+            "microverse \"./libportal.so\" :<\n    portal()\n>:",
+            "./libportal.so",
+        ),
+    ] {
+        let statement = grammar::statement(code).unwrap();
+        assert_eq!(
+            state.execute(&statement).unwrap_err().kind(),
+            &EKind::Sandboxed(name.to_string()),
+            "{}",
+            code
+        );
+    }
+}
+
+#[test]
+fn test_json() {
+    let mut state = State::new();
+    builtins::load(&mut state);
+    state.insert(
+        "text",
+        r#"{"name": "Rick", "age": 70, "height": 1.8, "pickle": true, "portal": null, "kids": ["Beth"]}"#,
+    );
+
+    let code = grammar::file(
+        r#"
+    parsed squanch parseJson(text)
+    back squanch toJson(parsed)
+    "#,
+    )
+    .unwrap();
+
+    state.run(&code).unwrap();
+
+    let pair = |key: &'static str, value: Value| Value::List(vec![Value::new(key), value]);
+    assert_eq!(
+        *state.get("parsed").unwrap(),
+        Value::List(vec![
+            pair("name", Value::new("Rick")),
+            pair("age", Value::new(70)),
+            pair("height", Value::new(1.8)),
+            pair("pickle", Value::new(true)),
+            pair("portal", Value::new(false)),
+            pair("kids", Value::new(vec!["Beth"])),
+        ])
+    );
+    assert_eq!(
+        *state.get("back").unwrap(),
+        Value::new(
+            r#"[["name","Rick"],["age",70],["height",1.8],["pickle",true],["portal",false],["kids",["Beth"]]]"#
+        )
+    );
+}
+
+#[test]
+fn test_json_errors() {
+    let mut state = State::new();
+    builtins::load(&mut state);
+    state.insert("text", "[1,\n  2,,]");
+
+    let parse = grammar::statement("x squanch parseJson(text)").unwrap();
+    match state.execute(&parse).unwrap_err().kind() {
+        EKind::InvalidJson { line, column, .. } => assert_eq!((*line, *column), (2, 5)),
+        kind => panic!("expected InvalidJson, got {:?}", kind),
+    }
+
+    let function = grammar::file("foo(x) :<\n    return x\n>:\nx squanch toJson(foo)").unwrap();
+    assert_eq!(
+        state.run(&function).unwrap_err().kind(),
+        &EKind::NotJson("function".into())
+    );
+}
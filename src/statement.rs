@@ -1,4 +1,4 @@
-use crate::expression::Expression;
+use crate::{expression::Expression, util};
 
 use std::{
     cmp,
@@ -19,13 +19,18 @@ pub enum StatementKind {
     Delete(String),
     Print(Expression),
     PrintNoNl(Expression),
+    PrintErr(Expression),
+    PrintErrNoNl(Expression),
+    Assert(Expression),
     ListNew(String),
-    ListAppend(String, Expression),
-    ListAssign(String, Expression, Expression),
-    ListDelete(String, Expression),
+    ListAppend(String, Vec<Expression>, Expression),
+    ListAssign(String, Vec<Expression>, Expression),
+    ListDelete(String, Vec<Expression>),
     If(Expression, Vec<Statement>, Option<Vec<Statement>>),
     While(Expression, Vec<Statement>),
     Input(String),
+    InputRaw(String),
+    InputAll(String),
     Catch(Vec<Statement>, Vec<Statement>),
     Function(String, Vec<String>, Vec<Statement>),
     Return(Expression),
@@ -62,7 +67,7 @@ impl StatementKind {
         S: Into<String>,
         E: Into<Expression>,
     {
-        StatementKind::ListAppend(name.into(), expr.into())
+        StatementKind::ListAppend(name.into(), Vec::new(), expr.into())
     }
 
     pub fn list_delete<S, E>(name: S, expr: E) -> Self
@@ -70,7 +75,7 @@ impl StatementKind {
         S: Into<String>,
         E: Into<Expression>,
     {
-        StatementKind::ListDelete(name.into(), expr.into())
+        StatementKind::ListDelete(name.into(), vec![expr.into()])
     }
 
     pub fn if_block<E>(
@@ -121,7 +126,7 @@ impl StatementKind {
         E: Into<Expression>,
         R: Into<Expression>,
     {
-        StatementKind::ListAssign(name.into(), index.into(), assign.into())
+        StatementKind::ListAssign(name.into(), vec![index.into()], assign.into())
     }
 
     pub fn delete<S>(name: S) -> Self
@@ -138,6 +143,20 @@ impl StatementKind {
         StatementKind::Print(expr.into())
     }
 
+    pub fn print_err<E>(expr: E) -> Self
+    where
+        E: Into<Expression>,
+    {
+        StatementKind::PrintErr(expr.into())
+    }
+
+    pub fn assert<E>(expr: E) -> Self
+    where
+        E: Into<Expression>,
+    {
+        StatementKind::Assert(expr.into())
+    }
+
     pub fn new_list<S>(name: S) -> Self
     where
         S: Into<String>,
@@ -160,6 +179,19 @@ impl Statement {
         }
     }
 
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    /// The 1-based line and column that this statement starts at within `source`.
+    pub fn location(&self, source: &str) -> (usize, usize) {
+        util::line_col(source, self.start)
+    }
+
     pub fn get_source(&self, filename: &str) -> io::Result<String> {
         let mut source = String::new();
         let mut f = File::open(filename)?;
@@ -171,6 +203,33 @@ impl Statement {
     }
 }
 
+/// Call `f` on every statement in `statements`, including the ones nested in blocks.
+pub fn walk<'a, F>(statements: &'a [Statement], f: &mut F)
+where
+    F: FnMut(&'a Statement),
+{
+    for statement in statements {
+        f(statement);
+
+        match statement.kind {
+            StatementKind::If(_, ref if_body, ref else_body) => {
+                walk(if_body, f);
+                if let Some(ref else_body) = *else_body {
+                    walk(else_body, f);
+                }
+            }
+            StatementKind::While(_, ref body) | StatementKind::Function(_, _, ref body) => {
+                walk(body, f)
+            }
+            StatementKind::Catch(ref try_block, ref catch) => {
+                walk(try_block, f);
+                walk(catch, f);
+            }
+            _ => {}
+        }
+    }
+}
+
 #[cfg(test)]
 impl cmp::PartialEq<StatementKind> for Statement {
     fn eq(&self, kind: &StatementKind) -> bool {
@@ -0,0 +1,148 @@
+use std::{cell::OnceCell, cmp::Ordering, fmt, hash, ops::Deref};
+
+/// The string type behind `Value::Str`. Lengths, indices and slices all count unicode scalar
+/// values (Rust `char`s), and indexing by one of those is constant time instead of walking the
+/// whole string to find it.
+#[derive(Clone, Default)]
+pub struct SwString {
+    text: String,
+    /// The byte offset of each char, or `None` when the string is all ASCII and every char is one
+    /// byte. Worked out the first time it's needed, since most strings are never indexed.
+    offsets: OnceCell<Option<Box<[usize]>>>,
+}
+
+impl SwString {
+    pub fn new(text: String) -> Self {
+        Self {
+            text,
+            offsets: OnceCell::new(),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    pub fn into_string(self) -> String {
+        self.text
+    }
+
+    fn offsets(&self) -> Option<&[usize]> {
+        self.offsets
+            .get_or_init(|| {
+                if self.text.is_ascii() {
+                    None
+                } else {
+                    Some(self.text.char_indices().map(|(idx, _)| idx).collect())
+                }
+            })
+            .as_deref()
+    }
+
+    /// The byte offset of the char at `index`, where `index` can be one past the last char.
+    fn byte_offset(&self, index: usize) -> usize {
+        match self.offsets() {
+            Some(offsets) => offsets.get(index).copied().unwrap_or(self.text.len()),
+            None => index,
+        }
+    }
+
+    /// The number of chars in the string.
+    pub fn char_len(&self) -> usize {
+        match self.offsets() {
+            Some(offsets) => offsets.len(),
+            None => self.text.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    /// The char at `index` as a string of its own.
+    pub fn get(&self, index: usize) -> Option<&str> {
+        if index < self.char_len() {
+            Some(self.slice(index, index + 1))
+        } else {
+            None
+        }
+    }
+
+    /// The chars from `start` up to but not including `end`, which must both be at most
+    /// `char_len()`.
+    pub fn slice(&self, start: usize, end: usize) -> &str {
+        &self.text[self.byte_offset(start)..self.byte_offset(end)]
+    }
+
+    /// Replace the chars from `start` up to but not including `end` with `with`.
+    pub fn splice(&mut self, start: usize, end: usize, with: &str) {
+        let range = self.byte_offset(start)..self.byte_offset(end);
+
+        self.text.replace_range(range, with);
+        self.offsets = OnceCell::new();
+    }
+}
+
+impl Deref for SwString {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.text
+    }
+}
+
+impl fmt::Display for SwString {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.text, f)
+    }
+}
+
+impl fmt::Debug for SwString {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&self.text, f)
+    }
+}
+
+impl PartialEq for SwString {
+    fn eq(&self, other: &Self) -> bool {
+        self.text == other.text
+    }
+}
+
+impl Eq for SwString {}
+
+impl PartialOrd for SwString {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SwString {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.text.cmp(&other.text)
+    }
+}
+
+impl hash::Hash for SwString {
+    fn hash<H: hash::Hasher>(&self, state: &mut H) {
+        self.text.hash(state)
+    }
+}
+
+impl From<String> for SwString {
+    fn from(from: String) -> Self {
+        Self::new(from)
+    }
+}
+
+impl From<&str> for SwString {
+    fn from(from: &str) -> Self {
+        Self::new(from.to_string())
+    }
+}
+
+impl From<SwString> for String {
+    fn from(from: SwString) -> Self {
+        from.text
+    }
+}
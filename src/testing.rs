@@ -0,0 +1,105 @@
+use crate::{
+    builtins,
+    error::ErrorWithContext,
+    state::State,
+    statement::{walk, Statement, StatementKind},
+};
+use std::{
+    fs, io, mem,
+    path::{Path, PathBuf},
+};
+
+const TEST_FILE_SUFFIX: &str = "_test.y";
+const TEST_FUNCTION_PREFIX: &str = "test_";
+
+/// A test function, which is any function with no parameters whose name starts with `test_`.
+pub struct Test<'a> {
+    pub name: &'a str,
+    pub place: &'a Statement,
+    pub body: &'a [Statement],
+}
+
+/// Add every test file in `path` to `files`. Directories are searched recursively for files
+/// ending in `_test.y`, skipping hidden ones, and any other path is taken to be a test file.
+pub fn find_test_files(path: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    if !path.is_dir() {
+        files.push(path.into());
+        return Ok(());
+    }
+
+    let mut entries = fs::read_dir(path)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    entries.sort();
+
+    for entry in entries {
+        let name = match entry.file_name().and_then(|name| name.to_str()) {
+            Some(name) => name,
+            None => continue,
+        };
+
+        if entry.is_dir() {
+            if !name.starts_with('.') {
+                find_test_files(&entry, files)?;
+            }
+        } else if name.ends_with(TEST_FILE_SUFFIX) {
+            files.push(entry);
+        }
+    }
+
+    Ok(())
+}
+
+/// The test functions defined at the top level of a file, in the order they were written.
+pub fn tests(statements: &[Statement]) -> Vec<Test<'_>> {
+    statements
+        .iter()
+        .filter_map(|statement| match statement.kind {
+            StatementKind::Function(ref name, ref params, ref body)
+                if name.starts_with(TEST_FUNCTION_PREFIX) && params.is_empty() =>
+            {
+                Some(Test {
+                    name,
+                    place: statement,
+                    body,
+                })
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Run a single test in a fresh state that has the builtins and the top level of its file loaded.
+///
+/// The body runs in that state rather than being called, so unlike a regular function it can
+/// see everything the file defines.
+pub fn run_test(
+    filename: &str,
+    statements: &[Statement],
+    test: &Test,
+) -> Result<(), ErrorWithContext> {
+    let mut state = State::new();
+    state.parse_args(&[]);
+    state.set_program(filename);
+
+    builtins::load(&mut state);
+
+    let result = state.run(statements).and_then(|()| state.run(test.body));
+
+    // Native functions from microverses can't outlive their libraries, just like in run_program
+    mem::forget(state);
+
+    result
+}
+
+/// Whether `place` is one of the statements in `statements`, rather than in another file like
+/// the builtins.
+pub fn contains(statements: &[Statement], place: &Statement) -> bool {
+    let mut found = false;
+
+    walk(statements, &mut |statement| {
+        found |= statement == place;
+    });
+
+    found
+}
@@ -0,0 +1,57 @@
+use crate::{hook::Hook, state::State, statement::Statement, util::LineIndex};
+use std::io::Write;
+
+/// Prints every statement as it runs, indented by how many function calls deep it is.
+pub struct Tracer<W> {
+    filename: String,
+    source: String,
+    lines: LineIndex,
+    output: W,
+    depth: usize,
+}
+
+impl<W> Tracer<W>
+where
+    W: Write,
+{
+    pub fn new(filename: &str, source: &str, output: W) -> Self {
+        Self {
+            filename: filename.into(),
+            source: source.into(),
+            lines: LineIndex::new(source),
+            output,
+            depth: 0,
+        }
+    }
+}
+
+impl<W> Hook for Tracer<W>
+where
+    W: Write,
+{
+    fn statement(&mut self, _state: &mut State, statement: &Statement) {
+        let line = self.lines.line(statement.start());
+        let text = self.source[statement.start()..statement.end()]
+            .lines()
+            .next()
+            .unwrap_or_default();
+
+        writeln!(
+            self.output,
+            "[trace] {}{}:{}: {}",
+            "  ".repeat(self.depth),
+            self.filename,
+            line,
+            text
+        )
+        .ok();
+    }
+
+    fn enter_function(&mut self, _name: &str) {
+        self.depth += 1;
+    }
+
+    fn exit_function(&mut self, _name: &str) {
+        self.depth -= 1;
+    }
+}
@@ -37,3 +37,46 @@ pub fn slice_value_format(x: &[Value]) -> String {
 
     s
 }
+
+/// Convert a byte offset into `source` to a 1-based line and column.
+pub fn line_col(source: &str, offset: usize) -> (usize, usize) {
+    let mut line = 1;
+    let mut column = 1;
+
+    for (idx, c) in source.char_indices() {
+        if idx >= offset {
+            break;
+        }
+
+        if c == '\n' {
+            line += 1;
+            column = 1;
+        } else {
+            column += 1;
+        }
+    }
+
+    (line, column)
+}
+
+/// Finds the line that a byte offset is on without rescanning the whole source every time.
+pub struct LineIndex {
+    starts: Vec<usize>,
+}
+
+impl LineIndex {
+    pub fn new(source: &str) -> Self {
+        let mut starts = vec![0];
+        starts.extend(source.match_indices('\n').map(|(idx, _)| idx + 1));
+
+        Self { starts }
+    }
+
+    /// The 1-based line that `offset` is on.
+    pub fn line(&self, offset: usize) -> usize {
+        match self.starts.binary_search(&offset) {
+            Ok(idx) => idx + 1,
+            Err(idx) => idx,
+        }
+    }
+}
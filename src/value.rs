@@ -1,11 +1,15 @@
 use crate::{
-    error::{ErrorKind, SwResult},
+    builtins::{Builtin, Capability},
+    error::{EitherError, ErrorKind, SwResult},
+    format,
     statement::Statement,
+    string::SwString,
     util, Operator,
 };
-use lazy_static::*;
-use regex::Regex;
-use std::{clone, cmp::Ordering, f64 as FloatValueType, fmt};
+use std::{
+    borrow::Cow, clone, cmp::Ordering, collections::TryReserveError, convert::TryFrom, fmt, io,
+    slice,
+};
 
 pub type FloatT = f64;
 pub type IntT = i64;
@@ -20,12 +24,17 @@ pub type _Func = unsafe extern "C" fn(*mut Vec<Value>) -> *mut SwResult<Value>;
 pub type _FuncSymbol = Symbol<_Func>;
 
 pub struct Func {
-    f: _FuncSymbol,
+    f: FuncKind,
+}
+
+enum FuncKind {
+    Dylib(_FuncSymbol),
+    Builtin(&'static Builtin),
 }
 
 #[derive(Debug, Clone)]
 pub enum Value {
-    Str(String),
+    Str(SwString),
     Int(IntT),
     Float(FloatT),
     Bool(bool),
@@ -85,17 +94,34 @@ impl fmt::Debug for Func {
 
 impl clone::Clone for Func {
     fn clone(&self) -> Self {
-        panic!("You cannot clone a native function.")
+        match self.f {
+            FuncKind::Builtin(builtin) => builtin.into(),
+            FuncKind::Dylib(_) => panic!("You cannot clone a native function."),
+        }
     }
 }
 
 impl Func {
     pub fn new(f: _FuncSymbol) -> Self {
-        Self { f }
+        Self {
+            f: FuncKind::Dylib(f),
+        }
+    }
+
+    /// What the program has to be allowed to do to call this function, if anything.
+    pub fn capability(&self) -> Option<Capability> {
+        match self.f {
+            FuncKind::Builtin(builtin) => builtin.capability,
+            FuncKind::Dylib(_) => None,
+        }
     }
 
     pub fn call(&self, args: &mut Vec<Value>) -> SwResult<Value> {
-        let f = &self.f;
+        let f = match self.f {
+            FuncKind::Dylib(ref f) => f,
+            FuncKind::Builtin(builtin) => return builtin.call(args),
+        };
+
         let val = unsafe {
             let result = f(args as *mut Vec<Value>);
             if result.is_null() {
@@ -111,13 +137,21 @@ impl Func {
 
 impl From<_FuncSymbol> for Value {
     fn from(from: _FuncSymbol) -> Self {
-        Value::NativeFunction(Func { f: from })
+        Value::NativeFunction(Func::new(from))
     }
 }
 
 impl From<_FuncSymbol> for Func {
     fn from(from: _FuncSymbol) -> Self {
-        Self { f: from }
+        Self::new(from)
+    }
+}
+
+impl From<&'static Builtin> for Func {
+    fn from(from: &'static Builtin) -> Self {
+        Self {
+            f: FuncKind::Builtin(from),
+        }
     }
 }
 
@@ -147,7 +181,7 @@ impl From<bool> for Value {
 
 impl From<String> for Value {
     fn from(from: String) -> Self {
-        Value::Str(from)
+        Value::Str(from.into())
     }
 }
 
@@ -195,7 +229,7 @@ impl Value {
     pub fn len(&self) -> SwResult<usize> {
         use self::Value::*;
         match *self {
-            Str(ref s) => Ok(s.chars().count()),
+            Str(ref s) => Ok(s.char_len()),
             List(ref l) => Ok(l.len()),
             _ => Err(ErrorKind::UnexpectedType {
                 expected: Type::Union(Box::new(Type::Str), Box::new(Type::List)),
@@ -218,14 +252,88 @@ impl Value {
         }
     }
 
+    /// The element of a list or the character of a string at `index`, which counts back from the
+    /// end if it's negative.
+    pub fn index(&self, index: IntT) -> SwResult<Cow<'_, Self>> {
+        match *self {
+            Value::List(ref l) => Ok(Cow::Borrowed(&l[resolve_index(index, l.len())?])),
+            Value::Str(ref s) => {
+                let index = resolve_index(index, s.char_len())?;
+                Ok(Cow::Owned(Value::Str(s.slice(index, index + 1).into())))
+            }
+            _ => Err(ErrorKind::IndexUnindexable(self.get_type()).into()),
+        }
+    }
+
+    /// Take every `step`th element from `start` up to but not including `end`, like Python does.
+    /// Negative bounds count back from the end, bounds past either end are clamped to it and a
+    /// negative step walks backwards. Leaving out a bound means going all the way to that end.
+    pub fn slice(&self, start: Option<IntT>, end: Option<IntT>, step: IntT) -> SwResult<Self> {
+        if step == 0 {
+            return Err(ErrorKind::ZeroStep.into());
+        }
+
+        let len = self.len()? as IntT;
+        let (lower, upper) = if step > 0 { (0, len) } else { (-1, len - 1) };
+        let bound = |bound: Option<IntT>, default| match bound {
+            None => default,
+            Some(i) if i < 0 => (i + len).max(lower),
+            Some(i) => i.min(upper),
+        };
+
+        let (start, end) = if step > 0 {
+            (bound(start, lower), bound(end, upper))
+        } else {
+            (bound(start, upper), bound(end, lower))
+        };
+
+        if step == 1 {
+            let (start, end) = (start as usize, end.max(start) as usize);
+            return Ok(match *self {
+                Value::Str(ref s) => Value::Str(s.slice(start, end).into()),
+                Value::List(ref l) => Value::List(l[start..end].to_vec()),
+                _ => unreachable!("only strings and lists have a length"),
+            });
+        }
+
+        let mut indices = Vec::new();
+        let mut next = Some(start);
+        while let Some(idx) = next {
+            if (step > 0 && idx >= end) || (step < 0 && idx <= end) {
+                break;
+            }
+
+            indices.push(idx as usize);
+            // A step big enough to overflow is past the end anyway
+            next = idx.checked_add(step);
+        }
+
+        Ok(match *self {
+            Value::Str(ref s) => Value::Str(
+                indices
+                    .into_iter()
+                    .map(|i| s.slice(i, i + 1))
+                    .collect::<String>()
+                    .into(),
+            ),
+            Value::List(ref l) => Value::List(indices.into_iter().map(|i| l[i].clone()).collect()),
+            _ => unreachable!("only strings and lists have a length"),
+        })
+    }
+
     #[cfg(feature = "debug_printing")]
-    pub fn print(&self) {
-        print!("{:?}", self);
+    pub fn write<W: io::Write + ?Sized>(&self, out: &mut W) -> io::Result<()> {
+        write!(out, "{:?}", self)
     }
 
     #[cfg(not(feature = "debug_printing"))]
+    pub fn write<W: io::Write + ?Sized>(&self, out: &mut W) -> io::Result<()> {
+        write!(out, "{}", self)
+    }
+
+    /// Print to stdout. Use `write` to print anywhere else.
     pub fn print(&self) {
-        print!("{}", self);
+        self.write(&mut io::stdout()).unwrap();
     }
 
     pub fn println(&self) {
@@ -233,18 +341,6 @@ impl Value {
         println!();
     }
 
-    fn assert_f32(&self) -> SwResult<FloatT> {
-        match *self {
-            Value::Float(f) => Ok(f),
-            Value::Int(i) => Ok(i as FloatT),
-            _ => Err(ErrorKind::UnexpectedType {
-                expected: Type::Float,
-                actual: self.get_type(),
-            }
-            .into()),
-        }
-    }
-
     fn assert_bool(&self) -> SwResult<bool> {
         match *self {
             Value::Bool(b) => Ok(b),
@@ -256,47 +352,73 @@ impl Value {
         }
     }
 
+    /// `!` is logical for bools and flips every bit of an int.
     pub fn not(&self) -> SwResult<Self> {
         match *self {
             Value::Bool(b) => Ok(Value::Bool(!b)),
+            Value::Int(i) => Ok(Value::Int(!i)),
             _ => Err(ErrorKind::UnexpectedType {
-                expected: Type::Bool,
+                expected: Type::Union(Box::new(Type::Bool), Box::new(Type::Int)),
                 actual: self.get_type(),
             }
             .into()),
         }
     }
 
-    pub fn less_than(&self, other: &Self) -> SwResult<Self> {
-        if let (&Value::Int(i1), &Value::Int(i2)) = (self, other) {
-            Ok(Value::Bool(i1 < i2))
-        } else {
-            Ok(Value::Bool(self.assert_f32()? < other.assert_f32()?))
+    /// Compare two values for `<` and friends. Numbers are compared by value, and strings and lists
+    /// are compared element by element. It's `None` if there's a NaN in the way, which makes every
+    /// comparison false, and an error if the values can't be compared at all.
+    fn compare(&self, other: &Self, operator: Operator) -> SwResult<Option<Ordering>> {
+        match (self, other) {
+            (Value::List(ref l1), Value::List(ref l2)) => {
+                for (v1, v2) in l1.iter().zip(l2) {
+                    match v1.compare(v2, operator.clone())? {
+                        Some(Ordering::Equal) => {}
+                        ordering => return Ok(ordering),
+                    }
+                }
+
+                Ok(Some(l1.len().cmp(&l2.len())))
+            }
+            (Value::Int(_), Value::Int(_))
+            | (Value::Int(_), Value::Float(_))
+            | (Value::Float(_), Value::Int(_))
+            | (Value::Float(_), Value::Float(_))
+            | (Value::Str(_), Value::Str(_))
+            | (Value::Bool(_), Value::Bool(_)) => Ok(self.partial_cmp(other)),
+            _ => {
+                Err(
+                    ErrorKind::InvalidBinaryExpression(self.get_type(), other.get_type(), operator)
+                        .into(),
+                )
+            }
         }
     }
 
+    pub fn less_than(&self, other: &Self) -> SwResult<Self> {
+        let ordering = self.compare(other, Operator::LessThan)?;
+        Ok(Value::Bool(ordering == Some(Ordering::Less)))
+    }
+
     pub fn greater_than(&self, other: &Self) -> SwResult<Self> {
-        if let (&Value::Int(i1), &Value::Int(i2)) = (self, other) {
-            Ok(Value::Bool(i1 > i2))
-        } else {
-            Ok(Value::Bool(self.assert_f32()? > other.assert_f32()?))
-        }
+        let ordering = self.compare(other, Operator::GreaterThan)?;
+        Ok(Value::Bool(ordering == Some(Ordering::Greater)))
     }
 
     pub fn greater_than_equal(&self, other: &Self) -> SwResult<Self> {
-        if let (&Value::Int(i1), &Value::Int(i2)) = (self, other) {
-            Ok(Value::Bool(i1 >= i2))
-        } else {
-            Ok(Value::Bool(self.assert_f32()? >= other.assert_f32()?))
-        }
+        let ordering = self.compare(other, Operator::GreaterThanEqual)?;
+        Ok(Value::Bool(matches!(
+            ordering,
+            Some(Ordering::Greater) | Some(Ordering::Equal)
+        )))
     }
 
     pub fn less_than_equal(&self, other: &Self) -> SwResult<Self> {
-        if let (&Value::Int(i1), &Value::Int(i2)) = (self, other) {
-            Ok(Value::Bool(i1 <= i2))
-        } else {
-            Ok(Value::Bool(self.assert_f32()? <= other.assert_f32()?))
-        }
+        let ordering = self.compare(other, Operator::LessThanEqual)?;
+        Ok(Value::Bool(matches!(
+            ordering,
+            Some(Ordering::Less) | Some(Ordering::Equal)
+        )))
     }
 
     pub fn and(&self, other: &Self) -> SwResult<Self> {
@@ -312,9 +434,23 @@ impl Value {
     }
 
     pub fn modulus(&self, other: &Self) -> SwResult<Self> {
+        if let Value::Str(ref template) = *self {
+            let args = match *other {
+                Value::List(ref args) => args.as_slice(),
+                ref arg => slice::from_ref(arg),
+            };
+
+            return Ok(Value::Str(format::format(template, args)?.into()));
+        }
+
         if let Value::Int(i1) = *self {
             if let Value::Int(i2) = *other {
-                Ok((i1 % i2).into())
+                if i2 == 0 {
+                    Err(ErrorKind::DivisionByZero(Operator::Modulus).into())
+                } else {
+                    // The only way this can overflow is `i64::MIN % -1`, which is 0 anyway
+                    Ok(i1.wrapping_rem(i2).into())
+                }
             } else {
                 Err(ErrorKind::UnexpectedType {
                     expected: Type::Int,
@@ -334,15 +470,16 @@ impl Value {
     pub fn add(&self, other: &Self) -> SwResult<Self> {
         match (self, other) {
             (Value::Float(f1), Value::Float(f2)) => Ok(Value::Float(f1 + f2)),
-            (Value::Int(i1), Value::Int(i2)) => Ok(Value::Int(i1 + i2)),
+            (Value::Int(i1), Value::Int(i2)) => checked(i1.checked_add(*i2), Operator::Add),
             (Value::Float(f), Value::Int(i)) | (Value::Int(i), Value::Float(f)) => {
                 Ok(Value::Float(*i as FloatT + f))
             }
             (Value::Str(ref s1), Value::Str(ref s2)) => {
-                let mut new_buf = s1.clone();
+                let mut new_buf = s1.to_string();
                 new_buf.push_str(s2);
-                Ok(Value::Str(new_buf))
+                Ok(Value::Str(new_buf.into()))
             }
+            (Value::List(ref l1), Value::List(ref l2)) => Ok(Value::List([&l1[..], l2].concat())),
             _ => Err(ErrorKind::InvalidBinaryExpression(
                 self.get_type(),
                 other.get_type(),
@@ -355,7 +492,7 @@ impl Value {
     pub fn subtract(&self, other: &Self) -> SwResult<Self> {
         match (self, other) {
             (Value::Float(f1), Value::Float(f2)) => Ok(Value::Float(f1 - f2)),
-            (Value::Int(i1), Value::Int(i2)) => Ok(Value::Int(i1 - i2)),
+            (Value::Int(i1), Value::Int(i2)) => checked(i1.checked_sub(*i2), Operator::Subtract),
             (Value::Float(f), Value::Int(i)) => Ok(Value::Float(f - *i as FloatT)),
             (Value::Int(i), Value::Float(f)) => Ok(Value::Float(*i as FloatT - f)),
             _ => Err(ErrorKind::InvalidBinaryExpression(
@@ -370,16 +507,27 @@ impl Value {
     pub fn multiply(&self, other: &Self) -> SwResult<Self> {
         match (self, other) {
             (Value::Float(f1), Value::Float(f2)) => Ok(Value::Float(f1 * f2)),
-            (Value::Int(i1), Value::Int(i2)) => Ok(Value::Int(i1 * i2)),
+            (Value::Int(i1), Value::Int(i2)) => checked(i1.checked_mul(*i2), Operator::Multiply),
             (Value::Float(f), Value::Int(i)) | (Value::Int(i), Value::Float(f)) => {
                 Ok(Value::Float(*i as FloatT * f))
             }
             (Value::Str(ref s), Value::Int(i)) => {
-                let mut new_buf = s.clone();
-                for _ in 0..(i - 1) {
-                    new_buf.push_str(s);
+                let (times, total) = repeat_count(s.as_str().len(), *i)?;
+                let mut repeated = String::new();
+                repeated.try_reserve_exact(total).map_err(repeat_overflow)?;
+                for _ in 0..times {
+                    repeated.push_str(s.as_str());
                 }
-                Ok(Value::Str(new_buf))
+                Ok(Value::Str(repeated.into()))
+            }
+            (Value::List(ref l), Value::Int(i)) => {
+                let (times, total) = repeat_count(l.len(), *i)?;
+                let mut repeated = Vec::new();
+                repeated.try_reserve_exact(total).map_err(repeat_overflow)?;
+                for _ in 0..times {
+                    repeated.extend_from_slice(l);
+                }
+                Ok(Value::List(repeated))
             }
             _ => Err(ErrorKind::InvalidBinaryExpression(
                 self.get_type(),
@@ -390,24 +538,150 @@ impl Value {
         }
     }
 
-    pub fn divide(&self, other: &Self) -> SwResult<Self> {
+    /// Both numbers as floats, if at least one of them is a float and the other is a number.
+    fn float_operands(&self, other: &Self) -> Option<(FloatT, FloatT)> {
         match (self, other) {
-            (Value::Float(f1), Value::Float(f2)) => Ok(Value::Float(f1 / f2)),
-            (Value::Int(i1), Value::Int(i2)) => Ok(Value::Int(i1 / i2)),
-            (Value::Float(f), Value::Int(i)) => Ok(Value::Float(f / *i as FloatT)),
-            (Value::Int(i), Value::Float(f)) => Ok(Value::Float(*i as FloatT / f)),
-            _ => Err(ErrorKind::InvalidBinaryExpression(
-                self.get_type(),
-                other.get_type(),
-                Operator::Divide,
-            )
-            .into()),
+            (Value::Float(f1), Value::Float(f2)) => Some((*f1, *f2)),
+            (Value::Float(f), Value::Int(i)) => Some((*f, *i as FloatT)),
+            (Value::Int(i), Value::Float(f)) => Some((*i as FloatT, *f)),
+            _ => None,
+        }
+    }
+
+    /// Dividing by zero is an error, unless `ieee_floats` is set and it's a float division, which
+    /// gives infinity or NaN instead.
+    pub fn divide(&self, other: &Self, ieee_floats: bool) -> SwResult<Self> {
+        match (self, other) {
+            (Value::Int(_), Value::Int(0)) => {
+                Err(ErrorKind::DivisionByZero(Operator::Divide).into())
+            }
+            (Value::Int(i1), Value::Int(i2)) => checked(i1.checked_div(*i2), Operator::Divide),
+            _ => match self.float_operands(other) {
+                Some((_, divisor)) if divisor == 0.0 && !ieee_floats => {
+                    Err(ErrorKind::DivisionByZero(Operator::Divide).into())
+                }
+                Some((dividend, divisor)) => Ok(Value::Float(dividend / divisor)),
+                None => Err(ErrorKind::InvalidBinaryExpression(
+                    self.get_type(),
+                    other.get_type(),
+                    Operator::Divide,
+                )
+                .into()),
+            },
+        }
+    }
+
+    /// Divide and round down, towards negative infinity rather than towards zero like `/` does
+    /// for ints. Zero is handled the same way as for `divide`.
+    pub fn floor_divide(&self, other: &Self, ieee_floats: bool) -> SwResult<Self> {
+        match (self, other) {
+            (Value::Int(_), Value::Int(0)) => {
+                Err(ErrorKind::DivisionByZero(Operator::FloorDivide).into())
+            }
+            (Value::Int(i1), Value::Int(i2)) => {
+                let quotient = i1.checked_div(*i2).map(|quotient| {
+                    if i1.wrapping_rem(*i2) != 0 && (*i1 < 0) != (*i2 < 0) {
+                        quotient - 1
+                    } else {
+                        quotient
+                    }
+                });
+
+                checked(quotient, Operator::FloorDivide)
+            }
+            _ => match self.float_operands(other) {
+                Some((_, divisor)) if divisor == 0.0 && !ieee_floats => {
+                    Err(ErrorKind::DivisionByZero(Operator::FloorDivide).into())
+                }
+                Some((dividend, divisor)) => Ok(Value::Float((dividend / divisor).floor())),
+                None => Err(ErrorKind::InvalidBinaryExpression(
+                    self.get_type(),
+                    other.get_type(),
+                    Operator::FloorDivide,
+                )
+                .into()),
+            },
         }
     }
 
+    /// Raise to a power. Ints stay ints unless the exponent is negative, and raising zero to a
+    /// negative power is a division by zero.
+    pub fn power(&self, other: &Self, ieee_floats: bool) -> SwResult<Self> {
+        match (self, other) {
+            (Value::Int(base), Value::Int(exponent)) if *exponent >= 0 => {
+                let result = match u32::try_from(*exponent) {
+                    Ok(exponent) => base.checked_pow(exponent),
+                    // Nothing else survives being multiplied by itself this many times
+                    Err(_) => match *base {
+                        0 | 1 => Some(*base),
+                        -1 if exponent % 2 == 0 => Some(1),
+                        -1 => Some(-1),
+                        _ => None,
+                    },
+                };
+
+                checked(result, Operator::Power)
+            }
+            (Value::Int(base), Value::Int(exponent)) => {
+                Value::Float(*base as FloatT).power(&Value::Float(*exponent as FloatT), ieee_floats)
+            }
+            _ => match self.float_operands(other) {
+                Some((base, exponent)) if base == 0.0 && exponent < 0.0 && !ieee_floats => {
+                    Err(ErrorKind::DivisionByZero(Operator::Power).into())
+                }
+                Some((base, exponent)) => Ok(Value::Float(base.powf(exponent))),
+                None => Err(ErrorKind::InvalidBinaryExpression(
+                    self.get_type(),
+                    other.get_type(),
+                    Operator::Power,
+                )
+                .into()),
+            },
+        }
+    }
+
+    fn bitwise(
+        &self,
+        other: &Self,
+        operator: Operator,
+        function: fn(IntT, IntT) -> IntT,
+    ) -> SwResult<Self> {
+        match (self, other) {
+            (Value::Int(i1), Value::Int(i2)) => Ok(Value::Int(function(*i1, *i2))),
+            _ => {
+                Err(
+                    ErrorKind::InvalidBinaryExpression(self.get_type(), other.get_type(), operator)
+                        .into(),
+                )
+            }
+        }
+    }
+
+    pub fn bit_and(&self, other: &Self) -> SwResult<Self> {
+        self.bitwise(other, Operator::BitAnd, |i1, i2| i1 & i2)
+    }
+
+    pub fn bit_or(&self, other: &Self) -> SwResult<Self> {
+        self.bitwise(other, Operator::BitOr, |i1, i2| i1 | i2)
+    }
+
+    pub fn bit_xor(&self, other: &Self) -> SwResult<Self> {
+        self.bitwise(other, Operator::BitXor, |i1, i2| i1 ^ i2)
+    }
+
     pub fn shift_left(&self, other: &Self) -> SwResult<Self> {
         match (self, other) {
-            (Value::Int(i1), Value::Int(i2)) => Ok(Value::Int(i1 << i2)),
+            (Value::Int(i1), Value::Int(i2)) => {
+                let shift = shift_amount(*i2)?;
+                let shifted = i1 << shift;
+
+                // Shifting back has to give the same number, or bits fell off the end
+                if shifted >> shift == *i1 {
+                    Ok(Value::Int(shifted))
+                } else {
+                    Err(ErrorKind::Overflow(Operator::ShiftLeft).into())
+                }
+            }
             _ => Err(ErrorKind::InvalidBinaryExpression(
                 self.get_type(),
                 other.get_type(),
@@ -419,7 +693,7 @@ impl Value {
 
     pub fn shift_right(&self, other: &Self) -> SwResult<Self> {
         match (self, other) {
-            (Value::Int(i1), Value::Int(i2)) => Ok(Value::Int(i1 >> i2)),
+            (Value::Int(i1), Value::Int(i2)) => Ok(Value::Int(i1 >> shift_amount(*i2)?)),
             _ => Err(ErrorKind::InvalidBinaryExpression(
                 self.get_type(),
                 other.get_type(),
@@ -432,20 +706,95 @@ impl Value {
 
 impl PartialOrd for Value {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        if self == other {
-            Option::Some(Ordering::Equal)
-        } else {
-            let (s, o) = match (self, other) {
-                (Value::Int(i), Value::Float(f)) => (*i as FloatT, *f),
-                (Value::Float(f), Value::Int(i)) => (*f, *i as FloatT),
-                _ => return Option::None,
-            };
-
-            s.partial_cmp(&o)
+        match (self, other) {
+            (Value::Int(i1), Value::Int(i2)) => Some(i1.cmp(i2)),
+            (Value::Float(f1), Value::Float(f2)) => f1.partial_cmp(f2),
+            (Value::Int(i), Value::Float(f)) => compare_int_float(*i, *f),
+            (Value::Float(f), Value::Int(i)) => compare_int_float(*i, *f).map(Ordering::reverse),
+            (Value::Str(ref s1), Value::Str(ref s2)) => Some(s1.cmp(s2)),
+            (Value::Bool(b1), Value::Bool(b2)) => Some(b1.cmp(b2)),
+            (Value::List(ref l1), Value::List(ref l2)) => l1.partial_cmp(l2),
+            _ => None,
         }
     }
 }
 
+/// Compare an int with a float exactly. Converting the int to a float would round anything past
+/// 2^53, which would make some different numbers equal.
+fn compare_int_float(i: IntT, f: FloatT) -> Option<Ordering> {
+    // 2^63, the first float past the end of the ints
+    const INT_END: FloatT = 9_223_372_036_854_775_808.0;
+
+    if f.is_nan() {
+        None
+    } else if f >= INT_END {
+        Some(Ordering::Less)
+    } else if f < -INT_END {
+        Some(Ordering::Greater)
+    } else {
+        // The whole part of `f` fits in an int now, so only the fraction is left to break a tie
+        let whole = f.trunc();
+        Some(
+            i.cmp(&(whole as IntT))
+                .then_with(|| whole.partial_cmp(&f).unwrap()),
+        )
+    }
+}
+
+/// Wrap up the result of a checked integer operation, which is `None` if it overflowed.
+fn checked(result: Option<IntT>, operator: Operator) -> SwResult<Value> {
+    match result {
+        Some(i) => Ok(Value::Int(i)),
+        None => Err(ErrorKind::Overflow(operator).into()),
+    }
+}
+
+/// How many times to repeat something `len` long and how long the result will be. Negative counts
+/// repeat it zero times, and so does an empty `len`, since there's nothing to repeat.
+fn repeat_count(len: usize, times: IntT) -> SwResult<(usize, usize)> {
+    let times = if len == 0 {
+        0
+    } else {
+        usize::try_from(times).unwrap_or(0)
+    };
+
+    match len.checked_mul(times) {
+        Some(total) => Ok((times, total)),
+        None => Err(ErrorKind::Overflow(Operator::Multiply).into()),
+    }
+}
+
+/// Anything too big to allocate is too big to be the result of a multiplication, so reserving
+/// the whole result up front gives an overflow instead of aborting the process.
+fn repeat_overflow(_: TryReserveError) -> EitherError {
+    ErrorKind::Overflow(Operator::Multiply).into()
+}
+
+/// Shifting by a negative number or by all of the bits in an int or more doesn't mean anything.
+fn shift_amount(amount: IntT) -> SwResult<u32> {
+    if (0..IntT::BITS.into()).contains(&amount) {
+        Ok(amount as u32)
+    } else {
+        Err(ErrorKind::InvalidShift(amount).into())
+    }
+}
+
+/// Turn an index into an offset into something `len` long. Negative indexes count back from the
+/// end, so `-1` is the last element.
+pub(crate) fn resolve_index(index: IntT, len: usize) -> Result<usize, ErrorKind> {
+    let resolved = if index < 0 {
+        index + len as IntT
+    } else {
+        index
+    };
+
+    if resolved >= 0 && (resolved as usize) < len {
+        Ok(resolved as usize)
+    } else {
+        Err(ErrorKind::IndexOutOfBounds { len, index })
+    }
+}
+
 impl PartialEq for Value {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
@@ -454,18 +803,11 @@ impl PartialEq for Value {
             (Value::List(ref l1), Value::List(ref l2)) => l1 == l2,
             (Value::Int(i1), Value::Int(i2)) => i1 == i2,
             (Value::Int(i), Value::Float(f)) | (Value::Float(f), Value::Int(i)) => {
-                (*i as FloatT - f).abs() < FloatValueType::EPSILON
+                compare_int_float(*i, *f) == Some(Ordering::Equal)
             }
-            (Value::Float(f1), Value::Float(f2)) => (f1 - f2).abs() < FloatValueType::EPSILON,
+            // Exactly equal, so NaN isn't equal to anything, not even itself
+            (Value::Float(f1), Value::Float(f2)) => f1 == f2,
             _ => false,
         }
     }
 }
-
-pub fn string_parse(string: &str) -> String {
-    lazy_static! {
-        static ref NEWLINE: Regex = Regex::new("[^\\\\]?\\\\n").unwrap();
-    }
-
-    NEWLINE.replace_all(string, "\n").into()
-}
@@ -52,6 +52,10 @@ where
         self.find(k).map(|idx| self.data.remove(idx).value)
     }
 
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.data.iter().map(|entry| (&entry.key, &entry.value))
+    }
+
     fn find<Q: ?Sized>(&self, k: &Q) -> Option<usize>
     where
         K: Borrow<Q>,
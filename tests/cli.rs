@@ -1,4 +1,8 @@
-use std::{fs::File, io::Read};
+use std::{
+    fs::File,
+    io::{Read, Write},
+    process::{Command, Stdio},
+};
 
 #[test]
 fn test_hello_world() {
@@ -27,3 +31,273 @@ fn test_brainfuck() {
         .contains("Hello World!")
         .unwrap();
 }
+
+#[test]
+fn test_brainfuck_file() {
+    assert_cli::Assert::main_binary()
+        .with_args(&["examples/brainfuck.y", "examples/hello.brainfuck"])
+        .stdout()
+        .is("Hello World!")
+        .unwrap();
+}
+
+#[test]
+fn test_debugger() {
+    assert_cli::Assert::main_binary()
+        .with_args(&["--debug", "--break", "19", "examples/fibonacci.y", "5"])
+        .stdin("print (count + 100)\ncount squanch 4\ncontinue\n")
+        .stderr()
+        .contains("-> examples/fibonacci.y:19")
+        .and()
+        .stderr()
+        .contains("100")
+        .and()
+        .stdout()
+        .contains("[1]")
+        .unwrap();
+}
+
+#[test]
+fn test_debugger_stepping() {
+    // The lines the debugger paused on, given the commands typed in at each pause
+    let paused = |commands: &str| -> Vec<usize> {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_schwift"))
+            .args(["--debug", "--break", "17", "examples/isPrime.y"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(commands.as_bytes())
+            .unwrap();
+
+        let output = child.wait_with_output().unwrap();
+        assert!(output.status.success());
+
+        String::from_utf8(output.stderr)
+            .unwrap()
+            .split("-> examples/isPrime.y:")
+            .skip(1)
+            .map(|pause| pause.split('\t').next().unwrap().parse().unwrap())
+            .collect()
+    };
+
+    // Into isPrime, over the statements in it and back out to where it was called from
+    assert_eq!(paused("s\nn\no\nc\n"), vec![17, 2, 4, 18]);
+
+    // Over the call without stopping in it
+    assert_eq!(paused("n\nc\n"), vec![17, 18]);
+}
+
+#[test]
+fn test_trace() {
+    assert_cli::Assert::main_binary()
+        .with_args(&["--trace", "examples/function.y"])
+        .stderr()
+        .contains("[trace] examples/function.y:8: foo(10, \"hello\")")
+        .and()
+        .stderr()
+        .contains("[trace]   examples/function.y:2: show me what you got x")
+        .and()
+        .stdout()
+        .is("10\nhello")
+        .unwrap();
+}
+
+#[test]
+fn test_profile() {
+    assert_cli::Assert::main_binary()
+        .with_args(&["--profile", "examples/fibonacci.y", "5"])
+        .stderr()
+        .contains("examples/fibonacci.y:19         5")
+        .unwrap();
+}
+
+#[test]
+fn test_profile_functions() {
+    let folded = std::env::temp_dir().join("schwift-function.folded");
+
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_schwift"))
+        .args(["--profile", "--profile-folded"])
+        .arg(&folded)
+        .arg("examples/function.y")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    // Each function's row is its name, call count, inclusive time and exclusive time
+    let report = String::from_utf8(output.stderr).unwrap();
+    let row = |name: &str| -> (u64, f64, f64) {
+        let fields: Vec<&str> = report
+            .lines()
+            .map(|line| line.split_whitespace().collect::<Vec<_>>())
+            .find(|fields| fields.first() == Some(&name))
+            .unwrap();
+
+        (
+            fields[1].parse().unwrap(),
+            fields[2].parse().unwrap(),
+            fields[3].parse().unwrap(),
+        )
+    };
+
+    let (main_calls, main_inclusive, main_exclusive) = row("main");
+    let (foo_calls, foo_inclusive, foo_exclusive) = row("foo");
+
+    assert_eq!(main_calls, 1);
+    assert_eq!(foo_calls, 1);
+    assert_eq!(foo_inclusive, foo_exclusive);
+    assert!(main_inclusive >= foo_inclusive);
+    assert!((main_exclusive - (main_inclusive - foo_inclusive)).abs() < 0.002);
+
+    let folded = std::fs::read_to_string(folded).unwrap();
+    let stacks: Vec<&str> = folded
+        .lines()
+        .map(|line| line.rsplit_once(' ').unwrap().0)
+        .collect();
+    assert_eq!(stacks, vec!["main", "main;foo"]);
+}
+
+#[test]
+fn test_coverage() {
+    let report = std::env::temp_dir().join("schwift-fibonacci.info");
+
+    assert_cli::Assert::main_binary()
+        .with_args(&[
+            "--coverage",
+            report.to_str().unwrap(),
+            "examples/fibonacci.y",
+            "5",
+        ])
+        .unwrap();
+
+    let report = std::fs::read_to_string(report).unwrap();
+    assert!(report.contains("SF:examples/fibonacci.y"));
+    assert!(report.contains("DA:2,0"));
+    assert!(report.contains("BRDA:1,0,1,1"));
+    assert!(report.ends_with("end_of_record\n"));
+}
+
+#[test]
+fn test_run_file_named_like_subcommand() {
+    assert_cli::Assert::main_binary()
+        .with_args(&["tests/schwift/args.y", "test", "lint"])
+        .stdout()
+        .is(r#"["test", "lint"]"#)
+        .unwrap();
+}
+
+#[test]
+fn test_test_runner() {
+    assert_cli::Assert::main_binary()
+        .with_args(&["test", "tests/schwift/math_test.y"])
+        .stdout()
+        .contains("test test_still_isolated ... ok")
+        .and()
+        .stdout()
+        .contains("test result: ok. 4 passed; 0 failed")
+        .unwrap();
+}
+
+#[test]
+fn test_lint_test_file() {
+    assert_cli::Assert::main_binary()
+        .with_args(&["lint", "tests/schwift/math_test.y"])
+        .stdout()
+        .is("")
+        .unwrap();
+}
+
+#[test]
+fn test_test_runner_failures() {
+    assert_cli::Assert::main_binary()
+        .with_args(&["test", "tests/schwift"])
+        .fails_with(1)
+        .stdout()
+        .contains("test test_fails ... FAILED")
+        .and()
+        .stdout()
+        .contains("tests/schwift/failing_test.y:7:5: test_fails\n    wubba lubba dub dub (x == 3)")
+        .and()
+        .stdout()
+        .contains("test result: FAILED. 5 passed; 2 failed")
+        .unwrap();
+}
+
+#[test]
+fn test_exit_status_and_env() {
+    assert_cli::Assert::main_binary()
+        .with_args(&["tests/schwift/exit.y", "3"])
+        .with_env(assert_cli::Environment::inherit().insert("SCHWIFT_GREETING", "wubba lubba"))
+        .fails_with(3)
+        .stdout()
+        .is("tests/schwift/exit.y\nwubba lubba\nmorty")
+        .unwrap();
+
+    assert_cli::Assert::main_binary()
+        .with_args(&["tests/schwift/exit.y", "0"])
+        .succeeds()
+        .unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn test_sandbox() {
+    assert_cli::Assert::main_binary()
+        .with_args(&["tests/schwift/process.y"])
+        .stdout()
+        .is("wubba lubba")
+        .unwrap();
+
+    assert_cli::Assert::main_binary()
+        .with_args(&["--sandbox", "tests/schwift/process.y"])
+        .fails_with(1)
+        .stdout()
+        .contains("this universe is sandboxed")
+        .unwrap();
+}
+
+#[test]
+fn test_sandbox_denies_writing_files() {
+    let path = std::env::temp_dir().join("schwift-sandbox-write.txt");
+    std::fs::remove_file(&path).ok();
+
+    assert_cli::Assert::main_binary()
+        .with_args(&["--sandbox", "tests/schwift/write.y", path.to_str().unwrap()])
+        .fails_with(1)
+        .stdout()
+        .contains("not allowed to call writeFile")
+        .unwrap();
+
+    assert!(!path.exists());
+}
+
+#[test]
+fn test_sandbox_denies_microverses() {
+    assert_cli::Assert::main_binary()
+        .with_args(&["--sandbox", "tests/schwift/microverse.y"])
+        .fails_with(1)
+        .stdout()
+        .contains("not allowed to call ./tests/schwift/libnothing.so")
+        .unwrap();
+}
+
+#[test]
+fn test_ieee_floats() {
+    assert_cli::Assert::main_binary()
+        .with_args(&["tests/schwift/divide.y"])
+        .fails_with(1)
+        .stdout()
+        .contains("You can't Divide by zero Morty!")
+        .unwrap();
+
+    assert_cli::Assert::main_binary()
+        .with_args(&["--ieee-floats", "tests/schwift/divide.y"])
+        .stdout()
+        .is("inf")
+        .unwrap();
+}
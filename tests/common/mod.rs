@@ -0,0 +1,65 @@
+use serde_json::Value as Json;
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    process::{Child, ChildStdin, ChildStdout, Command, Stdio},
+};
+
+/// A running `schwift` subcommand that talks in `Content-Length` framed JSON messages.
+pub struct Connection {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl Connection {
+    pub fn spawn(subcommand: &str) -> Self {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_schwift"))
+            .arg(subcommand)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+
+        let stdin = child.stdin.take().unwrap();
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+
+        Self {
+            child,
+            stdin,
+            stdout,
+        }
+    }
+
+    pub fn send(&mut self, message: Json) {
+        let body = message.to_string();
+        write!(self.stdin, "Content-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+        self.stdin.flush().unwrap();
+    }
+
+    pub fn receive(&mut self) -> Json {
+        let mut length = 0;
+
+        loop {
+            let mut header = String::new();
+            self.stdout.read_line(&mut header).unwrap();
+
+            let header = header.trim();
+            if header.is_empty() {
+                break;
+            }
+
+            if let Some(value) = header.strip_prefix("Content-Length:") {
+                length = value.trim().parse().unwrap();
+            }
+        }
+
+        let mut body = vec![0; length];
+        self.stdout.read_exact(&mut body).unwrap();
+
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    pub fn wait(&mut self) {
+        self.child.wait().unwrap();
+    }
+}
@@ -0,0 +1,243 @@
+mod common;
+
+use common::Connection;
+use serde_json::{json, Value as Json};
+
+struct Client {
+    connection: Connection,
+    seq: u64,
+    output: String,
+}
+
+impl Client {
+    fn start() -> Self {
+        let mut client = Self {
+            connection: Connection::spawn("dap"),
+            seq: 0,
+            output: String::new(),
+        };
+
+        client.request("initialize", json!({ "adapterID": "schwift" }));
+
+        client
+    }
+
+    /// Read the next message that isn't program output.
+    fn receive(&mut self) -> Json {
+        loop {
+            let message = self.connection.receive();
+            if message["event"] == "output" {
+                self.output
+                    .push_str(message["body"]["output"].as_str().unwrap());
+            } else {
+                return message;
+            }
+        }
+    }
+
+    fn request(&mut self, command: &str, arguments: Json) -> Json {
+        self.seq += 1;
+        let seq = self.seq;
+
+        self.connection.send(json!({
+            "seq": seq,
+            "type": "request",
+            "command": command,
+            "arguments": arguments,
+        }));
+
+        loop {
+            let message = self.receive();
+            if message["type"] == "response" && message["request_seq"] == seq {
+                assert_eq!(message["success"], true, "{} failed: {}", command, message);
+                return message["body"].clone();
+            }
+        }
+    }
+
+    fn event(&mut self, event: &str) -> Json {
+        loop {
+            let message = self.receive();
+            if message["type"] == "event" && message["event"] == event {
+                return message["body"].clone();
+            }
+        }
+    }
+
+    fn launch(
+        &mut self,
+        program: &str,
+        args: Json,
+        stop_on_entry: bool,
+        breakpoints: &[u64],
+    ) -> Json {
+        self.request(
+            "launch",
+            json!({ "program": program, "args": args, "stopOnEntry": stop_on_entry }),
+        );
+        self.event("initialized");
+        let breakpoints = self.set_breakpoints(program, breakpoints);
+        self.request("configurationDone", json!({}));
+        breakpoints
+    }
+
+    fn set_breakpoints(&mut self, program: &str, lines: &[u64]) -> Json {
+        let breakpoints: Vec<Json> = lines.iter().map(|line| json!({ "line": line })).collect();
+        let body = self.request(
+            "setBreakpoints",
+            json!({ "source": { "path": program }, "breakpoints": breakpoints }),
+        );
+        body["breakpoints"].clone()
+    }
+
+    fn stack(&mut self) -> Vec<(String, u64)> {
+        let body = self.request("stackTrace", json!({ "threadId": 1 }));
+        body["stackFrames"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|frame| {
+                (
+                    frame["name"].as_str().unwrap().to_string(),
+                    frame["line"].as_u64().unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    fn finish(mut self) -> String {
+        let exited = self.event("exited");
+        assert_eq!(exited["exitCode"], 0);
+        self.event("terminated");
+
+        self.request("disconnect", json!({}));
+        self.connection.wait();
+
+        self.output
+    }
+}
+
+#[test]
+fn test_dap_breakpoints_and_variables() {
+    let mut client = Client::start();
+    client.launch("examples/fibonacci.y", json!(["5"]), false, &[19]);
+
+    let stopped = client.event("stopped");
+    assert_eq!(stopped["reason"], "breakpoint");
+    assert_eq!(
+        client.stack(),
+        vec![("examples/fibonacci.y".to_string(), 19)]
+    );
+
+    let trace = client.request("stackTrace", json!({ "threadId": 1 }));
+    assert_eq!(
+        trace["stackFrames"][0]["source"],
+        json!({ "path": "examples/fibonacci.y" })
+    );
+
+    let scopes = client.request("scopes", json!({ "frameId": 0 }));
+    let locals = scopes["scopes"][0]["variablesReference"].clone();
+    let variables = client.request("variables", json!({ "variablesReference": locals }));
+    let nums = variables["variables"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|v| v["name"] == "nums")
+        .unwrap()
+        .clone();
+    assert_eq!(nums["value"], "[1]");
+
+    let items = client.request(
+        "variables",
+        json!({ "variablesReference": nums["variablesReference"] }),
+    );
+    assert_eq!(items["variables"][0]["value"], "1");
+
+    // Variables without children have a reference of 0, and asking for those shouldn't crash
+    for arguments in &[json!({ "variablesReference": 0 }), json!({})] {
+        let none = client.request("variables", arguments.clone());
+        assert_eq!(none["variables"], json!([]));
+    }
+
+    let result = client.request("evaluate", json!({ "expression": "(a + b)" }));
+    assert_eq!(result["result"], "2");
+
+    client.set_breakpoints("examples/fibonacci.y", &[]);
+    client.request("continue", json!({ "threadId": 1 }));
+
+    assert_eq!(client.finish(), "[1, 1, 2, 3, 5]\n");
+}
+
+#[test]
+fn test_dap_breakpoint_verification() {
+    let mut client = Client::start();
+
+    // Breakpoints on lines without a statement move to the next one that has
+    let breakpoints = client.launch("examples/function.y", json!([]), true, &[4, 7, 9]);
+    let verified: Vec<(bool, u64)> = breakpoints
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|b| {
+            (
+                b["verified"].as_bool().unwrap(),
+                b["line"].as_u64().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(verified, vec![(true, 5), (true, 8), (false, 9)]);
+    assert_eq!(client.event("stopped")["reason"], "entry");
+
+    let other = client.set_breakpoints("examples/fibonacci.y", &[19]);
+    assert_eq!(other[0]["verified"], false);
+
+    let absolute = std::fs::canonicalize("examples/function.y").unwrap();
+    let same = client.set_breakpoints(absolute.to_str().unwrap(), &[2]);
+    assert_eq!(same[0]["verified"], true);
+
+    client.request("continue", json!({ "threadId": 1 }));
+    assert_eq!(client.event("stopped")["reason"], "breakpoint");
+    assert_eq!(client.stack()[0].1, 2);
+
+    client.request("continue", json!({ "threadId": 1 }));
+    assert_eq!(client.finish(), "10\nhello\n");
+}
+
+#[test]
+fn test_dap_stepping() {
+    let mut client = Client::start();
+    client.launch("examples/function.y", json!([]), true, &[]);
+
+    assert_eq!(client.event("stopped")["reason"], "entry");
+    assert_eq!(client.stack(), vec![("examples/function.y".to_string(), 1)]);
+
+    client.request("next", json!({ "threadId": 1 }));
+    assert_eq!(client.event("stopped")["reason"], "step");
+    assert_eq!(client.stack(), vec![("examples/function.y".to_string(), 8)]);
+
+    client.request("stepIn", json!({ "threadId": 1 }));
+    client.event("stopped");
+    assert_eq!(
+        client.stack(),
+        vec![
+            ("foo".to_string(), 2),
+            ("examples/function.y".to_string(), 8),
+        ]
+    );
+
+    // Only the function's own variables, not the builtins it can call
+    let scopes = client.request("scopes", json!({ "frameId": 1 }));
+    let locals = scopes["scopes"][0]["variablesReference"].clone();
+    let variables = client.request("variables", json!({ "variablesReference": locals }));
+    let names: Vec<&str> = variables["variables"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|v| v["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, vec!["x", "y"]);
+
+    client.request("stepOut", json!({ "threadId": 1 }));
+
+    assert_eq!(client.finish(), "10\nhello\n");
+}
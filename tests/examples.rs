@@ -0,0 +1,140 @@
+//! Runs every program in `examples/` and compares what it does against the files checked in to
+//! `tests/examples/`. For an example called `name.y` those are:
+//!
+//! - `name.stdin`, fed to the program if it exists
+//! - `name.args`, whitespace separated arguments for the program if it exists
+//! - `name.stdout.expected`, `name.stderr.expected` and `name.status.expected`
+//!
+//! Run with `SCHWIFT_BLESS=1` to write the expected files from what the programs do now.
+
+use std::{
+    env,
+    ffi::OsStr,
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+const EXPECTED_DIR: &str = "tests/examples";
+const BLESS_VAR: &str = "SCHWIFT_BLESS";
+
+/// Examples that can't be run here, and why.
+const SKIPPED: [(&str, &str); 1] = [("loading.y", "needs the microverse from link_test built")];
+
+struct Output {
+    stdout: String,
+    stderr: String,
+    status: String,
+}
+
+/// Errors end with a random quote, which would never match, so they're all replaced with this.
+fn normalize(output: &[u8]) -> String {
+    let mut output = String::from_utf8_lossy(output).into_owned();
+
+    for quote in &schwift::error::QUOTES {
+        output = output.replace(quote, "<quote>");
+    }
+
+    output
+}
+
+fn expected_path(name: &str, extension: &str) -> PathBuf {
+    Path::new(EXPECTED_DIR).join(format!("{}.{}", name, extension))
+}
+
+fn run(example: &Path, name: &str) -> Output {
+    let args = match fs::read_to_string(expected_path(name, "args")) {
+        Ok(args) => args.split_whitespace().map(String::from).collect(),
+        Err(_) => Vec::new(),
+    };
+    let stdin = fs::read(expected_path(name, "stdin")).unwrap_or_default();
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_schwift"))
+        .arg(example)
+        .args(&args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    // Programs that don't read all of their input can close stdin early, which is fine
+    child.stdin.take().unwrap().write_all(&stdin).ok();
+
+    let output = child.wait_with_output().unwrap();
+
+    Output {
+        stdout: normalize(&output.stdout),
+        stderr: normalize(&output.stderr),
+        status: match output.status.code() {
+            Some(code) => format!("{}\n", code),
+            None => "killed by a signal\n".into(),
+        },
+    }
+}
+
+#[test]
+fn test_examples() {
+    let bless = env::var_os(BLESS_VAR).is_some();
+
+    let mut examples: Vec<PathBuf> = fs::read_dir("examples")
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension() == Some(OsStr::new("y")))
+        .collect();
+    examples.sort();
+
+    let mut failures = Vec::new();
+
+    for example in &examples {
+        let file_name = example.file_name().unwrap().to_str().unwrap();
+        if SKIPPED.iter().any(|(skipped, _)| *skipped == file_name) {
+            continue;
+        }
+
+        let name = example.file_stem().unwrap().to_str().unwrap();
+        let output = run(example, name);
+
+        let streams = [
+            ("stdout", &output.stdout),
+            ("stderr", &output.stderr),
+            ("status", &output.status),
+        ];
+
+        for (stream, actual) in &streams {
+            let path = expected_path(name, &format!("{}.expected", stream));
+
+            if bless {
+                fs::write(&path, actual).unwrap();
+                continue;
+            }
+
+            match fs::read_to_string(&path) {
+                Ok(ref expected) if expected == *actual => {}
+                Ok(expected) => failures.push(format!(
+                    "{} {} didn't match {}\n--- expected\n{}\n--- actual\n{}",
+                    example.display(),
+                    stream,
+                    path.display(),
+                    expected,
+                    actual
+                )),
+                Err(e) => failures.push(format!(
+                    "{} has no expected {}, {}: {}",
+                    example.display(),
+                    stream,
+                    path.display(),
+                    e
+                )),
+            }
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "{}\n\nRun with {}=1 to update the expected output.",
+        failures.join("\n\n"),
+        BLESS_VAR
+    );
+}
@@ -0,0 +1,171 @@
+mod common;
+
+use common::Connection;
+use serde_json::{json, Value as Json};
+
+const URI: &str = "file:///tmp/test.y";
+
+const SOURCE: &str = r#"add(x, y) :<
+	return (x + y)
+>:
+
+z squanch add(1, 2)
+squanch z
+show me what you got z
+"#;
+
+struct Client {
+    connection: Connection,
+    next_id: u64,
+}
+
+impl Client {
+    fn start() -> Self {
+        let mut client = Self {
+            connection: Connection::spawn("lsp"),
+            next_id: 0,
+        };
+
+        client.request("initialize", json!({ "capabilities": {} }));
+        client.notify("initialized", json!({}));
+
+        client
+    }
+
+    fn send(&mut self, message: Json) {
+        self.connection.send(message);
+    }
+
+    fn receive(&mut self) -> Json {
+        self.connection.receive()
+    }
+
+    fn notify(&mut self, method: &str, params: Json) {
+        self.send(json!({ "jsonrpc": "2.0", "method": method, "params": params }));
+    }
+
+    fn request(&mut self, method: &str, params: Json) -> Json {
+        self.next_id += 1;
+        let id = self.next_id;
+
+        self.send(json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }));
+
+        loop {
+            let message = self.receive();
+            if message["id"] == id {
+                return message;
+            }
+        }
+    }
+
+    fn open(&mut self, text: &str) -> Json {
+        self.notify(
+            "textDocument/didOpen",
+            json!({
+                "textDocument": { "uri": URI, "languageId": "schwift", "version": 1, "text": text },
+            }),
+        );
+
+        self.receive()
+    }
+
+    fn at(&mut self, method: &str, line: u64, character: u64) -> Json {
+        self.request(
+            method,
+            json!({
+                "textDocument": { "uri": URI },
+                "position": { "line": line, "character": character },
+            }),
+        )["result"]
+            .clone()
+    }
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        self.request("shutdown", Json::Null);
+        self.notify("exit", Json::Null);
+        self.connection.wait();
+    }
+}
+
+#[test]
+fn test_lsp_publishes_syntax_errors() {
+    let mut client = Client::start();
+
+    let diagnostics = client.open("x squanch 10\nshow me what you got (x +\n");
+
+    assert_eq!(diagnostics["method"], "textDocument/publishDiagnostics");
+    let diagnostics = diagnostics["params"]["diagnostics"].as_array().unwrap();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0]["severity"], 1);
+    assert_eq!(diagnostics[0]["range"]["start"]["line"], 1);
+}
+
+#[test]
+fn test_lsp_publishes_lints() {
+    let mut client = Client::start();
+
+    let diagnostics = client.open(SOURCE);
+    let diagnostics = diagnostics["params"]["diagnostics"].as_array().unwrap();
+
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0]["severity"], 2);
+    assert_eq!(
+        diagnostics[0]["range"]["start"],
+        json!({ "line": 6, "character": 0 })
+    );
+}
+
+#[test]
+fn test_lsp_definition_and_hover() {
+    let mut client = Client::start();
+    client.open(SOURCE);
+
+    let definition = client.at("textDocument/definition", 4, 11);
+    assert_eq!(definition["uri"], URI);
+    assert_eq!(
+        definition["range"],
+        json!({
+            "start": { "line": 0, "character": 0 },
+            "end": { "line": 0, "character": 3 },
+        })
+    );
+
+    let hover = client.at("textDocument/hover", 4, 11);
+    assert_eq!(hover["contents"]["value"], "```schwift\nadd(x, y)\n```");
+
+    let hover = client.at("textDocument/hover", 6, 0);
+    assert_eq!(hover, Json::Null);
+}
+
+#[test]
+fn test_lsp_completion_and_symbols() {
+    let mut client = Client::start();
+    client.open(SOURCE);
+
+    let completion = client.at("textDocument/completion", 6, 0);
+    let labels: Vec<&str> = completion
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|item| item["label"].as_str().unwrap())
+        .collect();
+    assert!(labels.contains(&"on a cob"));
+    assert!(labels.contains(&"portal gun"));
+    assert!(labels.contains(&"add"));
+    assert!(labels.contains(&"ascii"));
+
+    let symbols = client.request(
+        "textDocument/documentSymbol",
+        json!({ "textDocument": { "uri": URI } }),
+    )["result"]
+        .clone();
+    let names: Vec<&str> = symbols
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|symbol| symbol["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, vec!["add", "z"]);
+}
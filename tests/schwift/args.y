@@ -0,0 +1 @@
+show me what you got argv
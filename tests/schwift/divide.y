@@ -0,0 +1,2 @@
+x squanch (1.0 / 0)
+show me what you got x
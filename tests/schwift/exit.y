@@ -0,0 +1,11 @@
+show me what you got program
+show me what you got env("SCHWIFT_GREETING")
+show me what you got env("SCHWIFT_NOT_SET")
+
+normal plan :<
+	exit(argv[0])
+>: plan for failure :<
+	show me what you got "exit shouldn't be caught"
+>:
+
+show me what you got "exit should have stopped the program"
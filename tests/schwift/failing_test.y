@@ -0,0 +1,12 @@
+test_passes() :<
+    wubba lubba dub dub rick
+>:
+
+test_fails() :<
+    x squanch 2
+    wubba lubba dub dub (x == 3)
+>:
+
+test_errors() :<
+    show me what you got nope
+>:
@@ -0,0 +1,28 @@
+double(x) :<
+    return (x * 2)
+>:
+
+counter squanch 0
+
+test_double() :<
+    wubba lubba dub dub (double(2) == 4)
+    wubba lubba dub dub (double(-3) == -6)
+>:
+
+test_lists() :<
+    cob on a cob
+    cob assimilate 1
+    cob assimilate 2
+    wubba lubba dub dub ((cob squanch) == 2)
+    wubba lubba dub dub (cob[1] == 2)
+>:
+
+test_isolated() :<
+    counter squanch (counter + 1)
+    wubba lubba dub dub (counter == 1)
+>:
+
+test_still_isolated() :<
+    counter squanch (counter + 1)
+    wubba lubba dub dub (counter == 1)
+>:
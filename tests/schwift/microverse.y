@@ -0,0 +1,5 @@
+microverse "./tests/schwift/libnothing.so" :<
+	nothing()
+>:
+
+show me what you got "loaded it"
@@ -0,0 +1,4 @@
+args on a cob
+args assimilate "wubba lubba"
+result squanch runProcess("echo", args)
+show me what you got! result[0]
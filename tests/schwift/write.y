@@ -0,0 +1,2 @@
+writeFile(argv[0], "squanch")
+show me what you got "wrote it"